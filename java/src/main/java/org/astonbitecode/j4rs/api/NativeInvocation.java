@@ -74,6 +74,13 @@ public interface NativeInvocation<T> extends ObjectValue, JsonValue {
      */
     NativeInvocation field(String fieldName);
 
+    /**
+     * Checks whether the object that is contained in this {@link NativeInvocation} is null.
+     *
+     * @return true if the contained object is null, false otherwise
+     */
+    boolean isNull();
+
     /**
      * Casts a the object that is contained in a NativeInvocation to an object of class clazz.
      *
@@ -101,4 +108,16 @@ public interface NativeInvocation<T> extends ObjectValue, JsonValue {
     static <T> NativeInvocation cloneInstance(NativeInvocation from) {
         return new JsonInvocationImpl(from.getObject(), from.getObjectClass());
     }
+
+    /**
+     * Creates a NativeInvocation that contains an object, which is treated as an instance of clazz.
+     *
+     * @param object The object to contain.
+     * @param clazz  The class that the object is treated as.
+     * @param <T>    Generically defined return type
+     * @return a {@link NativeInvocation} instance.
+     */
+    static <T> NativeInvocation wrap(T object, Class<T> clazz) {
+        return new JsonInvocationImpl(object, clazz);
+    }
 }
@@ -0,0 +1,404 @@
+/*
+ * Copyright 2018 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.api.deploy;
+
+import org.w3c.dom.Document;
+import org.w3c.dom.Element;
+import org.w3c.dom.Node;
+import org.xml.sax.SAXException;
+
+import javax.xml.XMLConstants;
+import javax.xml.parsers.DocumentBuilder;
+import javax.xml.parsers.DocumentBuilderFactory;
+import javax.xml.parsers.ParserConfigurationException;
+import java.io.IOException;
+import java.io.InputStream;
+import java.net.URL;
+import java.util.*;
+import java.util.regex.Matcher;
+import java.util.regex.Pattern;
+
+/**
+ * Resolves the transitive dependencies of Maven artifacts using the POMs of the defined repositories.
+ * <p>
+ * The resolution follows the rules of Maven: the POMs inherit from their parents, versions and scopes are taken from the
+ * <code>dependencyManagement</code> (including the imported BOMs), test, provided and system scoped dependencies are
+ * not transitive, optional dependencies are not transitive and exclusions apply to the whole subtree of a dependency.
+ * Version conflicts are resolved by the "nearest wins" strategy: the version that is nearest to the root of the
+ * tree wins and, for equal depths, the one that is declared first.
+ */
+public class MavenDependencyResolver {
+    private static final Set<String> NON_TRANSITIVE_SCOPES = new HashSet<>(Arrays.asList("test", "provided", "system"));
+    private static final Set<String> JAR_TYPES = new HashSet<>(Arrays.asList("jar", "bundle", "maven-plugin", "ejb"));
+    private static final Pattern PROPERTY_PATTERN = Pattern.compile("\\$\\{([^}]+)}");
+    private static final int MAX_INTERPOLATION_ROUNDS = 10;
+
+    private final List<String> repoBases = new ArrayList<>();
+    private final String deployTarget;
+    private final Map<String, Pom> poms = new HashMap<>();
+    private String lastError;
+
+    public MavenDependencyResolver(String deployTarget) {
+        this.deployTarget = deployTarget;
+    }
+
+    /**
+     * Adds a repository to search for POMs and jars. The repositories are searched in the order they are added.
+     *
+     * @param repoBase The base URL of the repository.
+     */
+    public void addRepository(String repoBase) {
+        repoBases.add(repoBase);
+    }
+
+    /**
+     * Resolves the dependency tree of an artifact.
+     *
+     * @return The root of the tree, which is the artifact itself.
+     * @throws IOException If a POM cannot be fetched or parsed, or if the version of a dependency is not defined.
+     */
+    public ResolvedDependency resolve(String groupId, String artifactId, String version, String qualifier) throws IOException {
+        try {
+            return resolveTree(groupId, artifactId, version, qualifier);
+        } catch (IOException error) {
+            lastError = error.getMessage();
+            throw error;
+        }
+    }
+
+    private ResolvedDependency resolveTree(String groupId, String artifactId, String version, String qualifier) throws IOException {
+        Pom rootPom = effectivePom(groupId, artifactId, version);
+        ResolvedDependency root = new ResolvedDependency(groupId, artifactId, version, qualifier, "", rootPom.hasJar());
+        Set<String> resolved = new HashSet<>();
+        resolved.add(conflictKey(groupId, artifactId, qualifier));
+
+        // Breadth first, so that the nearest version of an artifact is found first
+        Deque<PendingNode> pending = new ArrayDeque<>();
+        pending.add(new PendingNode(root, rootPom, Collections.emptySet()));
+        while (!pending.isEmpty()) {
+            PendingNode node = pending.poll();
+            boolean isRoot = node.resolved == root;
+            for (Dependency dependency : node.pom.dependencies) {
+                if (dependency.optional || NON_TRANSITIVE_SCOPES.contains(dependency.scope) || node.excludes(dependency)) {
+                    continue;
+                }
+                String dependencyVersion = dependency.version;
+                String scope = dependency.scope;
+                // The dependency management of the root applies to the whole tree
+                Dependency managed = rootPom.management.get(dependency.managementKey());
+                if (!isRoot && managed != null) {
+                    dependencyVersion = managed.version != null ? managed.version : dependencyVersion;
+                    scope = managed.scope != null ? managed.scope : scope;
+                    if (NON_TRANSITIVE_SCOPES.contains(scope)) {
+                        continue;
+                    }
+                }
+                if (dependencyVersion == null) {
+                    throw new IOException(String.format("The version of %s:%s that is a dependency of %s is not defined",
+                            dependency.groupId, dependency.artifactId, node.resolved));
+                }
+                if (!resolved.add(conflictKey(dependency.groupId, dependency.artifactId, dependency.classifier))) {
+                    continue;
+                }
+
+                Pom pom = effectivePom(dependency.groupId, dependency.artifactId, dependencyVersion);
+                ResolvedDependency child = new ResolvedDependency(
+                        dependency.groupId,
+                        dependency.artifactId,
+                        dependencyVersion,
+                        dependency.classifier,
+                        isRoot ? scopeOrDefault(scope) : transitiveScope(node.resolved.getScope(), scope),
+                        JAR_TYPES.contains(dependency.type));
+                node.resolved.getDependencies().add(child);
+                Set<String> exclusions = new HashSet<>(node.exclusions);
+                exclusions.addAll(dependency.exclusions);
+                pending.add(new PendingNode(child, pom, exclusions));
+            }
+        }
+        return root;
+    }
+
+    /**
+     * Resolves the dependency tree of an artifact and deploys the jars of all the artifacts of the tree.
+     *
+     * @return The root of the tree, which is the artifact itself.
+     * @throws IOException If the resolution fails, or if a jar cannot be found in any of the repositories.
+     */
+    public ResolvedDependency deploy(String groupId, String artifactId, String version, String qualifier) throws IOException {
+        try {
+            ResolvedDependency root = resolveTree(groupId, artifactId, version, qualifier);
+            deployTree(root);
+            return root;
+        } catch (IOException error) {
+            lastError = error.getMessage();
+            throw error;
+        }
+    }
+
+    /**
+     * @return The message of the error that made the last resolution or deployment fail, or null if none failed.
+     */
+    public String getLastError() {
+        return lastError;
+    }
+
+    private void deployTree(ResolvedDependency dependency) throws IOException {
+        if (dependency.hasJar()) {
+            IOException lastError = new IOException("No Maven repositories are defined");
+            boolean deployed = false;
+            for (String repoBase : repoBases) {
+                try {
+                    new SimpleMavenDeployer(repoBase, deployTarget).deploy(
+                            dependency.getGroupId(),
+                            dependency.getArtifactId(),
+                            dependency.getVersion(),
+                            dependency.getClassifier());
+                    deployed = true;
+                    break;
+                } catch (IOException error) {
+                    lastError = error;
+                }
+            }
+            if (!deployed) {
+                throw new IOException(String.format("Could not deploy %s: %s", dependency, lastError.getMessage()), lastError);
+            }
+        }
+        for (ResolvedDependency child : dependency.getDependencies()) {
+            deployTree(child);
+        }
+    }
+
+    Pom effectivePom(String groupId, String artifactId, String version) throws IOException {
+        String key = String.format("%s:%s:%s", groupId, artifactId, version);
+        Pom cached = poms.get(key);
+        if (cached != null) {
+            return cached;
+        }
+        Element project = fetchPom(groupId, artifactId, version).getDocumentElement();
+
+        Element parentElement = child(project, "parent");
+        Pom parent = null;
+        if (parentElement != null) {
+            parent = effectivePom(text(parentElement, "groupId"), text(parentElement, "artifactId"), text(parentElement, "version"));
+        }
+
+        Pom pom = new Pom();
+        pom.packaging = Optional.ofNullable(text(project, "packaging")).orElse("jar");
+        if (parent != null) {
+            pom.properties.putAll(parent.properties);
+            pom.properties.put("project.parent.groupId", parent.properties.get("project.groupId"));
+            pom.properties.put("project.parent.version", parent.properties.get("project.version"));
+        }
+        for (Element property : children(child(project, "properties"))) {
+            pom.properties.put(property.getTagName(), property.getTextContent().trim());
+        }
+        // The coordinates of the POM, not of the parent
+        pom.properties.put("project.groupId", groupId);
+        pom.properties.put("project.artifactId", artifactId);
+        pom.properties.put("project.version", version);
+        pom.properties.put("pom.groupId", groupId);
+        pom.properties.put("pom.artifactId", artifactId);
+        pom.properties.put("pom.version", version);
+
+        // The own managed dependencies override the ones of the parent, while the imported ones do not override anything
+        if (parent != null) {
+            pom.management.putAll(parent.management);
+        }
+        List<Dependency> imports = new ArrayList<>();
+        for (Element element : children(child(child(project, "dependencyManagement"), "dependencies"))) {
+            Dependency managed = Dependency.from(element, pom.properties);
+            if ("import".equals(managed.scope)) {
+                imports.add(managed);
+            } else {
+                pom.management.put(managed.managementKey(), managed);
+            }
+        }
+        for (Dependency imported : imports) {
+            for (Map.Entry<String, Dependency> entry : effectivePom(imported.groupId, imported.artifactId, imported.version).management.entrySet()) {
+                pom.management.putIfAbsent(entry.getKey(), entry.getValue());
+            }
+        }
+
+        if (parent != null) {
+            pom.dependencies.addAll(parent.dependencies);
+        }
+        for (Element element : children(child(project, "dependencies"))) {
+            Dependency dependency = Dependency.from(element, pom.properties);
+            Dependency managed = pom.management.get(dependency.managementKey());
+            if (managed != null) {
+                dependency.version = dependency.version != null ? dependency.version : managed.version;
+                dependency.scope = dependency.scope != null ? dependency.scope : managed.scope;
+                if (dependency.exclusions.isEmpty()) {
+                    dependency.exclusions.addAll(managed.exclusions);
+                }
+            }
+            pom.dependencies.add(dependency);
+        }
+
+        poms.put(key, pom);
+        return pom;
+    }
+
+    private Document fetchPom(String groupId, String artifactId, String version) throws IOException {
+        String pomName = String.format("%s-%s.pom", artifactId, version);
+        for (String repoBase : repoBases) {
+            String url = String.format("%s/%s/%s/%s/%s", repoBase, groupId.replace(".", "/"), artifactId, version, pomName);
+            try (InputStream stream = new URL(url).openStream()) {
+                return newDocumentBuilder().parse(stream);
+            } catch (IOException error) {
+                /* try the next repository */
+            } catch (SAXException error) {
+                throw new IOException(String.format("Invalid POM %s: %s", url, error.getMessage()), error);
+            }
+        }
+        throw new IOException(String.format("Could not find the POM of %s:%s:%s in the repositories %s",
+                groupId, artifactId, version, repoBases));
+    }
+
+    private static DocumentBuilder newDocumentBuilder() throws IOException {
+        try {
+            DocumentBuilderFactory factory = DocumentBuilderFactory.newInstance();
+            factory.setFeature(XMLConstants.FEATURE_SECURE_PROCESSING, true);
+            factory.setFeature("http://apache.org/xml/features/disallow-doctype-decl", true);
+            factory.setExpandEntityReferences(false);
+            return factory.newDocumentBuilder();
+        } catch (ParserConfigurationException error) {
+            throw new IOException(error);
+        }
+    }
+
+    static String interpolate(String value, Map<String, String> properties) {
+        if (value == null) {
+            return null;
+        }
+        String interpolated = value;
+        for (int round = 0; round < MAX_INTERPOLATION_ROUNDS && interpolated.contains("${"); round++) {
+            Matcher matcher = PROPERTY_PATTERN.matcher(interpolated);
+            StringBuffer sb = new StringBuffer();
+            while (matcher.find()) {
+                String property = properties.get(matcher.group(1));
+                matcher.appendReplacement(sb, Matcher.quoteReplacement(property != null ? property : matcher.group()));
+            }
+            matcher.appendTail(sb);
+            if (sb.toString().equals(interpolated)) {
+                break;
+            }
+            interpolated = sb.toString();
+        }
+        return interpolated;
+    }
+
+    static String transitiveScope(String parentScope, String scope) {
+        return "runtime".equals(parentScope) || "runtime".equals(scope) ? "runtime" : "compile";
+    }
+
+    private static String scopeOrDefault(String scope) {
+        return scope != null ? scope : "compile";
+    }
+
+    private static String conflictKey(String groupId, String artifactId, String classifier) {
+        return String.format("%s:%s:%s", groupId, artifactId, classifier == null ? "" : classifier);
+    }
+
+    private static Element child(Element parent, String name) {
+        if (parent != null) {
+            for (Element element : children(parent)) {
+                if (element.getTagName().equals(name)) {
+                    return element;
+                }
+            }
+        }
+        return null;
+    }
+
+    private static List<Element> children(Element parent) {
+        List<Element> elements = new ArrayList<>();
+        if (parent != null) {
+            for (Node node = parent.getFirstChild(); node != null; node = node.getNextSibling()) {
+                if (node instanceof Element) {
+                    elements.add((Element) node);
+                }
+            }
+        }
+        return elements;
+    }
+
+    private static String text(Element parent, String name) {
+        Element element = child(parent, name);
+        return element == null ? null : element.getTextContent().trim();
+    }
+
+    static class Pom {
+        String packaging;
+        final Map<String, String> properties = new HashMap<>();
+        final Map<String, Dependency> management = new LinkedHashMap<>();
+        final List<Dependency> dependencies = new ArrayList<>();
+
+        boolean hasJar() {
+            return JAR_TYPES.contains(packaging);
+        }
+    }
+
+    static class Dependency {
+        String groupId;
+        String artifactId;
+        String version;
+        String classifier;
+        String type;
+        String scope;
+        boolean optional;
+        final Set<String> exclusions = new HashSet<>();
+
+        static Dependency from(Element element, Map<String, String> properties) {
+            Dependency dependency = new Dependency();
+            dependency.groupId = interpolate(text(element, "groupId"), properties);
+            dependency.artifactId = interpolate(text(element, "artifactId"), properties);
+            dependency.version = interpolate(text(element, "version"), properties);
+            dependency.classifier = Optional.ofNullable(interpolate(text(element, "classifier"), properties)).orElse("");
+            dependency.type = Optional.ofNullable(interpolate(text(element, "type"), properties)).orElse("jar");
+            dependency.scope = interpolate(text(element, "scope"), properties);
+            dependency.optional = "true".equals(interpolate(text(element, "optional"), properties));
+            for (Element exclusion : children(child(element, "exclusions"))) {
+                dependency.exclusions.add(String.format("%s:%s",
+                        interpolate(text(exclusion, "groupId"), properties),
+                        interpolate(text(exclusion, "artifactId"), properties)));
+            }
+            return dependency;
+        }
+
+        String managementKey() {
+            return String.format("%s:%s:%s:%s", groupId, artifactId, type, classifier);
+        }
+    }
+
+    private static class PendingNode {
+        final ResolvedDependency resolved;
+        final Pom pom;
+        final Set<String> exclusions;
+
+        PendingNode(ResolvedDependency resolved, Pom pom, Set<String> exclusions) {
+            this.resolved = resolved;
+            this.pom = pom;
+            this.exclusions = exclusions;
+        }
+
+        boolean excludes(Dependency dependency) {
+            return exclusions.contains(dependency.groupId + ":" + dependency.artifactId)
+                    || exclusions.contains(dependency.groupId + ":*")
+                    || exclusions.contains("*:" + dependency.artifactId)
+                    || exclusions.contains("*:*");
+        }
+    }
+}
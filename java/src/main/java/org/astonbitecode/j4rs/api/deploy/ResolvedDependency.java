@@ -0,0 +1,80 @@
+/*
+ * Copyright 2018 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.api.deploy;
+
+import com.fasterxml.jackson.annotation.JsonIgnore;
+
+import java.util.ArrayList;
+import java.util.List;
+
+/**
+ * A node of a resolved Maven dependency tree.
+ */
+public class ResolvedDependency {
+    private final String groupId;
+    private final String artifactId;
+    private final String version;
+    private final String classifier;
+    private final String scope;
+    private final boolean hasJar;
+    private final List<ResolvedDependency> dependencies = new ArrayList<>();
+
+    ResolvedDependency(String groupId, String artifactId, String version, String classifier, String scope, boolean hasJar) {
+        this.groupId = groupId;
+        this.artifactId = artifactId;
+        this.version = version;
+        this.classifier = classifier == null ? "" : classifier;
+        this.scope = scope;
+        this.hasJar = hasJar;
+    }
+
+    public String getGroupId() {
+        return groupId;
+    }
+
+    public String getArtifactId() {
+        return artifactId;
+    }
+
+    public String getVersion() {
+        return version;
+    }
+
+    public String getClassifier() {
+        return classifier;
+    }
+
+    public String getScope() {
+        return scope;
+    }
+
+    @JsonIgnore
+    public boolean hasJar() {
+        return hasJar;
+    }
+
+    public List<ResolvedDependency> getDependencies() {
+        return dependencies;
+    }
+
+    @Override
+    public String toString() {
+        StringBuilder sb = new StringBuilder(String.format("%s:%s:%s", groupId, artifactId, version));
+        if (!classifier.isEmpty()) {
+            sb.append(":").append(classifier);
+        }
+        return sb.toString();
+    }
+}
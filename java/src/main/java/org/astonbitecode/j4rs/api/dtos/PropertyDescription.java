@@ -0,0 +1,44 @@
+/*
+ * Copyright 2018 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.api.dtos;
+
+/**
+ * Describes a property of a Java bean, as this is seen by the JSON (de)serialization.
+ * <p>
+ * The kind is one of boolean, number, string, array, object and any.
+ */
+public class PropertyDescription {
+    private String name;
+    private String type;
+    private String kind;
+
+    public PropertyDescription(String name, String type, String kind) {
+        this.name = name;
+        this.type = type;
+        this.kind = kind;
+    }
+
+    public String getName() {
+        return name;
+    }
+
+    public String getType() {
+        return type;
+    }
+
+    public String getKind() {
+        return kind;
+    }
+}
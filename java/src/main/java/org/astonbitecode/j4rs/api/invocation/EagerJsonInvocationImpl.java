@@ -61,6 +61,11 @@ public class EagerJsonInvocationImpl<T> implements NativeInvocation<T> {
         throw new RuntimeException("Not implemented yet. Please use the JsonInvocationImpl instead");
     }
 
+    @Override
+    public boolean isNull() {
+        return object == null;
+    }
+
     @Override
     public T getObject() {
         throw new RuntimeException("Not implemented yet. Please use the JsonInvocationImpl instead");
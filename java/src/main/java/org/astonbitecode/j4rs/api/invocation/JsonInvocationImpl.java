@@ -115,6 +115,11 @@ public class JsonInvocationImpl<T> implements NativeInvocation<T> {
         }
     }
 
+    @Override
+    public boolean isNull() {
+        return object == null;
+    }
+
     @Override
     public T getObject() {
         return object;
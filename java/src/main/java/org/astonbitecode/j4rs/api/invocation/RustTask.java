@@ -0,0 +1,137 @@
+/*
+ * Copyright 2020 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.api.invocation;
+
+import org.astonbitecode.j4rs.api.dtos.InvocationArg;
+import org.astonbitecode.j4rs.api.dtos.InvocationArgGenerator;
+
+import java.lang.ref.PhantomReference;
+import java.lang.ref.ReferenceQueue;
+import java.util.Set;
+import java.util.concurrent.Callable;
+import java.util.concurrent.ConcurrentHashMap;
+import java.util.concurrent.locks.ReentrantReadWriteLock;
+
+/**
+ * A task that is implemented by a Rust closure. It can be executed by any Java thread, as a {@link Runnable} or a {@link Callable}.
+ * <p>
+ * The Rust closure is released when the task is closed, or, at the latest, after the task is garbage collected.
+ */
+public class RustTask implements Runnable, Callable<Object>, AutoCloseable {
+    private static native InvocationArg runtask(long taskAddress);
+
+    private static native void droptask(long taskAddress);
+
+    private static InvocationArgGenerator gen = new InvocationArgGenerator();
+
+    private static final ReferenceQueue<RustTask> COLLECTED_TASKS = new ReferenceQueue<>();
+    // The releases need to be strongly reachable until they are done
+    private static final Set<TaskRelease> PENDING_RELEASES = ConcurrentHashMap.newKeySet();
+
+    static {
+        Thread releaser = new Thread(RustTask::releaseCollectedTasks, "j4rs-rust-task-releaser");
+        releaser.setDaemon(true);
+        releaser.start();
+    }
+
+    private final TaskRelease release;
+
+    public RustTask(long taskAddress) {
+        this.release = new TaskRelease(this, taskAddress);
+        PENDING_RELEASES.add(release);
+    }
+
+    /**
+     * Executes the Rust closure.
+     *
+     * @return The result of the closure, or null if the closure does not return a result.
+     * @throws org.astonbitecode.j4rs.errors.InvocationException if the closure fails
+     * @throws IllegalStateException                             if the task is closed
+     */
+    @Override
+    public Object call() {
+        // The closure cannot be released while it runs
+        ReentrantReadWriteLock.ReadLock readLock = release.lock.readLock();
+        readLock.lock();
+        try {
+            if (release.address == 0) {
+                throw new IllegalStateException("The Rust task is closed");
+            }
+            InvocationArg result = runtask(release.address);
+            if (result == null) {
+                return null;
+            } else {
+                return gen.generateArgObjects(new InvocationArg[]{result})[0].getObject();
+            }
+        } finally {
+            readLock.unlock();
+        }
+    }
+
+    @Override
+    public void run() {
+        call();
+    }
+
+    /**
+     * Releases the Rust closure. The task cannot be executed after it is closed.
+     *
+     * @throws IllegalStateException if the task is closed by its own closure
+     */
+    @Override
+    public void close() {
+        if (release.lock.getReadHoldCount() > 0) {
+            throw new IllegalStateException("A Rust task cannot be closed while it runs");
+        }
+        release.release();
+    }
+
+    private static void releaseCollectedTasks() {
+        while (true) {
+            try {
+                ((TaskRelease) COLLECTED_TASKS.remove()).release();
+            } catch (InterruptedException error) {
+                return;
+            } catch (Throwable error) {
+                System.err.println("Could not release a Rust task: " + error);
+            }
+        }
+    }
+
+    private static final class TaskRelease extends PhantomReference<RustTask> {
+        private final ReentrantReadWriteLock lock = new ReentrantReadWriteLock();
+        // Guarded by the lock. Zero after the release.
+        private long address;
+
+        TaskRelease(RustTask task, long address) {
+            super(task, COLLECTED_TASKS);
+            this.address = address;
+        }
+
+        void release() {
+            lock.writeLock().lock();
+            try {
+                if (address != 0) {
+                    long toRelease = address;
+                    address = 0;
+                    droptask(toRelease);
+                }
+            } finally {
+                lock.writeLock().unlock();
+                PENDING_RELEASES.remove(this);
+            }
+        }
+    }
+}
@@ -22,6 +22,7 @@ import org.astonbitecode.j4rs.errors.JsonCodecException;
 import org.astonbitecode.j4rs.json.Codec;
 
 import java.io.IOException;
+import java.util.Optional;
 
 public class JsonValueImpl implements JsonValue, ObjectValue {
     private Codec codec = new Codec();
@@ -32,12 +33,14 @@ public class JsonValueImpl implements JsonValue, ObjectValue {
 
     public JsonValueImpl(Object obj) {
         this.obj = obj;
+        // Optionals are encoded as their contained value, or as null if they are empty
+        Object toEncode = obj instanceof Optional ? ((Optional<?>) obj).orElse(null) : obj;
         try {
-            this.json = codec.encode(obj);
+            this.json = codec.encode(toEncode);
         } catch (JsonProcessingException error) {
             throw new JsonCodecException("While creating JsonValueCallbackImpl: Could not encode " + json, error);
         }
-        this.className = obj.getClass().getName();
+        this.className = obj != null ? obj.getClass().getName() : null;
     }
 
     public JsonValueImpl(String json, String className) {
@@ -16,18 +16,25 @@ package org.astonbitecode.j4rs.json;
 
 import com.fasterxml.jackson.core.JsonProcessingException;
 import com.fasterxml.jackson.core.type.TypeReference;
+import com.fasterxml.jackson.databind.JavaType;
 import com.fasterxml.jackson.databind.ObjectMapper;
+import com.fasterxml.jackson.databind.introspect.BeanPropertyDefinition;
+import org.astonbitecode.j4rs.api.dtos.PropertyDescription;
 import org.astonbitecode.j4rs.errors.JsonCodecException;
 import org.astonbitecode.j4rs.utils.Utils;
 
 import java.io.IOException;
 import java.util.Arrays;
+import java.util.Collection;
+import java.util.List;
 import java.util.Map;
+import java.util.stream.Collectors;
 
 public class Codec {
     private static final String RUST_FIELD = "Rust";
     private static final String JSON_FIELD = "json";
     private static final String CLASS_NAME_FIELD = "class_name";
+    private static final ObjectMapper DESCRIBING_MAPPER = new ObjectMapper();
     private ObjectMapper mapper = new ObjectMapper();
     TypeReference<Map<String, Object>[]> typeRef
             = new TypeReference<Map<String, Object>[]>() {
@@ -44,6 +51,43 @@ public class Codec {
         return mapper.writeValueAsString(obj);
     }
 
+    /**
+     * Describes the properties that can be deserialized for the class with the provided name.
+     *
+     * @param className The name of the class to describe.
+     * @return A List with the descriptions of the properties.
+     * @throws ClassNotFoundException If the class is not found.
+     */
+    public static List<PropertyDescription> describeProperties(String className) throws ClassNotFoundException {
+        JavaType javaType = DESCRIBING_MAPPER.constructType(Utils.forNameEnhanced(className));
+        return DESCRIBING_MAPPER.getDeserializationConfig().introspect(javaType).findProperties().stream()
+                .filter(BeanPropertyDefinition::couldDeserialize)
+                .map(property -> {
+                    Class<?> type = property.getRawPrimaryType();
+                    return new PropertyDescription(property.getName(), type.getName(), kindOf(type));
+                })
+                .collect(Collectors.toList());
+    }
+
+    static String kindOf(Class<?> type) {
+        if (type == boolean.class || type == Boolean.class) {
+            return "boolean";
+        } else if ((type.isPrimitive() && type != char.class) || Number.class.isAssignableFrom(type)) {
+            return "number";
+        } else if (type == char.class || type == Character.class || CharSequence.class.isAssignableFrom(type) || type.isEnum()) {
+            return "string";
+        } else if (type.isArray() || Collection.class.isAssignableFrom(type)) {
+            return "array";
+        } else if (Map.class.isAssignableFrom(type)) {
+            return "object";
+        } else if (type == Object.class || type.getName().startsWith("java.")) {
+            // Types like Object, Date or UUID may be deserialized from different JSON values
+            return "any";
+        } else {
+            return "object";
+        }
+    }
+
     public Object[] decodeArrayContents(String json) throws IOException {
         Map<String, Object>[] array = mapper.readValue(json, typeRef);
 
@@ -0,0 +1,95 @@
+/*
+ * Copyright 2019 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.utils;
+
+import org.astonbitecode.j4rs.errors.InvalidArgumentException;
+
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.Enumeration;
+import java.util.Iterator;
+import java.util.List;
+import java.util.stream.BaseStream;
+
+/**
+ * Iterates lazily over the elements of a Java object, retrieving them in batches.
+ * <p>
+ * It is used by Rust in order to minimize the calls that cross the JNI boundary.
+ */
+public class BatchIterator {
+    private static final int INITIAL_BATCH_CAPACITY = 1024;
+    private final Iterator<?> iterator;
+
+    private BatchIterator(Iterator<?> iterator) {
+        this.iterator = iterator;
+    }
+
+    /**
+     * Creates a BatchIterator out of an Iterable, an Iterator, an Enumeration, a Stream or an array.
+     *
+     * The parameter is generic in order for any object to match when the method is invoked from Rust.
+     *
+     * @param object The object to iterate.
+     * @param <T>    The type of the object to iterate.
+     * @return A BatchIterator over the elements of the object.
+     */
+    public static <T> BatchIterator of(T object) {
+        return new BatchIterator(iteratorOf(object));
+    }
+
+    /**
+     * Retrieves at most batchSize elements.
+     *
+     * @param batchSize The maximum number of elements to retrieve.
+     * @return A List with the retrieved elements. An empty List means that the iteration is over.
+     */
+    public List<Object> nextBatch(int batchSize) {
+        // Large batch sizes are upper limits, not expected sizes
+        List<Object> batch = new ArrayList<>(Math.min(batchSize, INITIAL_BATCH_CAPACITY));
+        while (batch.size() < batchSize && iterator.hasNext()) {
+            batch.add(iterator.next());
+        }
+        return batch;
+    }
+
+    static Iterator<?> iteratorOf(Object object) {
+        if (object instanceof Iterator) {
+            return (Iterator<?>) object;
+        } else if (object instanceof Iterable) {
+            return ((Iterable<?>) object).iterator();
+        } else if (object instanceof BaseStream) {
+            return ((BaseStream<?, ?>) object).iterator();
+        } else if (object instanceof Enumeration) {
+            Enumeration<?> enumeration = (Enumeration<?>) object;
+            return new Iterator<Object>() {
+                @Override
+                public boolean hasNext() {
+                    return enumeration.hasMoreElements();
+                }
+
+                @Override
+                public Object next() {
+                    return enumeration.nextElement();
+                }
+            };
+        } else if (object instanceof Object[]) {
+            return Arrays.asList((Object[]) object).iterator();
+        } else {
+            String className = object == null ? "null" : object.getClass().getName();
+            throw new InvalidArgumentException("Cannot iterate over an instance of " + className
+                    + ". An Iterable, Iterator, Enumeration, Stream or array is needed.");
+        }
+    }
+}
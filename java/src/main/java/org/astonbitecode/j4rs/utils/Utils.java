@@ -16,7 +16,9 @@ package org.astonbitecode.j4rs.utils;
 
 import org.astonbitecode.j4rs.api.dtos.GeneratedArg;
 
+import java.lang.reflect.Method;
 import java.util.Arrays;
+import java.util.Set;
 
 public class Utils {
     public static Class<?> forNameEnhanced(final String className) throws ClassNotFoundException {
@@ -40,7 +42,50 @@ public class Utils {
             case "void":
                 return void.class;
             default:
-                return Class.forName(className);
+                return forNameInLoadersAndModules(className);
+        }
+    }
+
+    // Searches the class in the j4rs class loader, the context class loader of the current thread
+    // and the modules of the boot layer, which include the resolved modules of the module path.
+    private static Class<?> forNameInLoadersAndModules(final String className) throws ClassNotFoundException {
+        try {
+            return Class.forName(className);
+        } catch (ClassNotFoundException error) {
+            ClassLoader contextClassLoader = Thread.currentThread().getContextClassLoader();
+            if (contextClassLoader != null) {
+                try {
+                    return Class.forName(className, true, contextClassLoader);
+                } catch (ClassNotFoundException ignored) {
+                    /* search in the modules */
+                }
+            }
+            Class<?> clazz = forNameInBootLayer(className);
+            if (clazz == null) {
+                throw error;
+            }
+            return clazz;
+        }
+    }
+
+    // The module API is accessed reflectively, in order to run on Java 8 as well
+    private static Class<?> forNameInBootLayer(final String className) {
+        try {
+            Class<?> moduleLayerClass = Class.forName("java.lang.ModuleLayer");
+            Class<?> moduleClass = Class.forName("java.lang.Module");
+            Object bootLayer = moduleLayerClass.getMethod("boot").invoke(null);
+            Set<?> modules = (Set<?>) moduleLayerClass.getMethod("modules").invoke(bootLayer);
+            Method forNameInModule = Class.class.getMethod("forName", moduleClass, String.class);
+            for (Object module : modules) {
+                Class<?> clazz = (Class<?>) forNameInModule.invoke(null, module, className);
+                if (clazz != null) {
+                    return clazz;
+                }
+            }
+            return null;
+        } catch (ReflectiveOperationException error) {
+            // No modules before Java 9
+            return null;
         }
     }
 
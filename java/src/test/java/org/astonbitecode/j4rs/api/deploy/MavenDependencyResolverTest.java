@@ -0,0 +1,59 @@
+/*
+ * Copyright 2018 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.api.deploy;
+
+import org.junit.Test;
+
+import java.io.File;
+import java.io.IOException;
+import java.nio.file.Files;
+import java.util.HashMap;
+import java.util.Map;
+
+public class MavenDependencyResolverTest {
+    @Test
+    public void interpolate() {
+        Map<String, String> properties = new HashMap<>();
+        properties.put("project.version", "1.2.3");
+        properties.put("library.version", "${project.version}");
+
+        assert (MavenDependencyResolver.interpolate("${library.version}", properties).equals("1.2.3"));
+        assert (MavenDependencyResolver.interpolate("v${project.version}-${unknown}", properties).equals("v1.2.3-${unknown}"));
+        assert (MavenDependencyResolver.interpolate(null, properties) == null);
+    }
+
+    @Test
+    public void transitiveScope() {
+        assert (MavenDependencyResolver.transitiveScope("compile", null).equals("compile"));
+        assert (MavenDependencyResolver.transitiveScope("compile", "runtime").equals("runtime"));
+        assert (MavenDependencyResolver.transitiveScope("runtime", "compile").equals("runtime"));
+    }
+
+    @Test
+    public void lastError() throws IOException {
+        File repository = Files.createTempDirectory("j4rs_maven_dependency_resolver").toFile();
+        MavenDependencyResolver resolver = new MavenDependencyResolver(repository.getAbsolutePath());
+        resolver.addRepository(repository.toURI().toString());
+        assert (resolver.getLastError() == null);
+        try {
+            resolver.resolve("org.example.j4rs", "missing", "1.0", "");
+            assert (false);
+        } catch (IOException error) {
+            assert (resolver.getLastError().equals(error.getMessage()));
+            assert (resolver.getLastError().contains("Could not find the POM of org.example.j4rs:missing:1.0"));
+        }
+        repository.delete();
+    }
+}
@@ -135,6 +135,14 @@ public class JsonInvocationImplTest {
 //        assert(casted.getObject().getClass().equals(Dummy.class));
     }
 
+    @Test
+    public void wrap() {
+        ChildDummy child = new ChildDummy();
+        NativeInvocation wrapped = NativeInvocation.<Dummy>wrap(child, Dummy.class);
+        assert (wrapped.getObject() == child);
+        assert (wrapped.getObjectClass().equals(Dummy.class));
+    }
+
     @Test(expected = InvocationException.class)
     public void castFailure() {
         NativeInvocation from = new JsonInvocationImpl(new ChildDummy(), ChildDummy.class);
@@ -17,12 +17,15 @@ package org.astonbitecode.j4rs.json;
 import com.fasterxml.jackson.core.JsonParseException;
 import com.fasterxml.jackson.databind.JsonMappingException;
 import org.astonbitecode.j4rs.api.dtos.InvocationArg;
+import org.astonbitecode.j4rs.api.dtos.PropertyDescription;
 import org.astonbitecode.j4rs.errors.JsonCodecException;
 import org.astonbitecode.j4rs.utils.Dummy;
 import org.astonbitecode.j4rs.utils.OtherDummy;
 import org.junit.Test;
 
 import java.util.Arrays;
+import java.util.Map;
+import java.util.stream.Collectors;
 
 public class CodecTest {
     private Codec codec = new Codec();
@@ -74,5 +77,20 @@ public class CodecTest {
         String json = "[{\"i\":3,\"j\":33}, {\"i\":333,\"j\":3333}]";
         codec.decodeArrayContents(json);
     }
-}
 
+    @Test
+    public void describeProperties() throws Exception {
+        Map<String, String> kinds = Codec.describeProperties("org.astonbitecode.j4rs.tests.MyBean").stream()
+                .collect(Collectors.toMap(PropertyDescription::getName, PropertyDescription::getKind));
+        assert (kinds.size() == 4);
+        assert (kinds.get("name").equals("string"));
+        assert (kinds.get("age").equals("number"));
+        assert (kinds.get("active").equals("boolean"));
+        assert (kinds.get("tags").equals("array"));
+    }
+
+    @Test(expected = ClassNotFoundException.class)
+    public void describePropertiesOfMissingClass() throws Exception {
+        Codec.describeProperties("org.astonbitecode.j4rs.utils.Nothing");
+    }
+}
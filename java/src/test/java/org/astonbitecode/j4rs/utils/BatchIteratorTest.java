@@ -0,0 +1,48 @@
+/*
+ * Copyright 2019 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.utils;
+
+import org.astonbitecode.j4rs.errors.InvalidArgumentException;
+import org.junit.Test;
+
+import java.util.Arrays;
+import java.util.Collections;
+import java.util.List;
+import java.util.stream.Stream;
+
+public class BatchIteratorTest {
+    @Test
+    public void iterateInBatches() {
+        BatchIterator iterator = BatchIterator.of(Arrays.asList(1, 2, 3));
+        assert (iterator.nextBatch(2).equals(Arrays.asList(1, 2)));
+        assert (iterator.nextBatch(2).equals(Collections.singletonList(3)));
+        assert (iterator.nextBatch(2).isEmpty());
+    }
+
+    @Test
+    public void iterateStreamsEnumerationsAndArrays() {
+        List<Object> fromStream = BatchIterator.of(Stream.of("a", "b")).nextBatch(10);
+        assert (fromStream.equals(Arrays.asList("a", "b")));
+        List<Object> fromEnumeration = BatchIterator.of(Collections.enumeration(Arrays.asList("a", "b"))).nextBatch(10);
+        assert (fromEnumeration.equals(Arrays.asList("a", "b")));
+        List<Object> fromArray = BatchIterator.of(new String[]{"a", "b"}).nextBatch(10);
+        assert (fromArray.equals(Arrays.asList("a", "b")));
+    }
+
+    @Test(expected = InvalidArgumentException.class)
+    public void iterateNonIterable() {
+        BatchIterator.of(3);
+    }
+}
@@ -0,0 +1,31 @@
+/*
+ * Copyright 2019 astonbitecode
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.astonbitecode.j4rs.utils;
+
+import org.junit.Test;
+
+public class UtilsTest {
+    @Test
+    public void forNameEnhanced() throws ClassNotFoundException {
+        assert (Utils.forNameEnhanced("int").equals(int.class));
+        assert (Utils.forNameEnhanced("java.lang.String").equals(String.class));
+        assert (Utils.forNameEnhanced("org.astonbitecode.j4rs.utils.Dummy").equals(Dummy.class));
+    }
+
+    @Test(expected = ClassNotFoundException.class)
+    public void forNameEnhancedNotFound() throws ClassNotFoundException {
+        Utils.forNameEnhanced("org.astonbitecode.j4rs.utils.NotExisting");
+    }
+}
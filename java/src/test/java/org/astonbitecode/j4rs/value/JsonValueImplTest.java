@@ -18,6 +18,8 @@ import org.astonbitecode.j4rs.api.value.JsonValueImpl;
 import org.astonbitecode.j4rs.utils.Dummy;
 import org.junit.Test;
 
+import java.util.Optional;
+
 public class JsonValueImplTest {
 
     @Test
@@ -46,4 +48,21 @@ public class JsonValueImplTest {
         assert json.equals("{\"i\":3}");
         assert obj.getI() == 3;
     }
+
+    @Test
+    public void fromNull() {
+        JsonValueImpl jvi = new JsonValueImpl(null);
+        String json = jvi.getJson();
+        assert json.equals("null");
+        assert jvi.getObject() == null;
+    }
+
+    @Test
+    public void fromOptional() {
+        JsonValueImpl present = new JsonValueImpl(Optional.of("This is a String"));
+        assert present.getJson().equals("\"This is a String\"");
+
+        JsonValueImpl empty = new JsonValueImpl(Optional.empty());
+        assert empty.getJson().equals("null");
+    }
 }
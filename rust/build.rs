@@ -46,6 +46,9 @@ fn main() -> Result<(), J4rsBuildError> {
     copy_jars_from_java(&source_jar_location)?;
     let _ = copy_jars_to_exec_directory(&out_dir)?;
     generate_src(&out_dir)?;
+    if env::var("CARGO_FEATURE_EMBEDDED_JAR").is_ok() {
+        generate_embedded_jar_src(&out_dir)?;
+    }
 
     Ok(())
 }
@@ -65,6 +68,28 @@ fn j4rs_version() -> &'static str {{
     Ok(())
 }
 
+// Generates the source that embeds the j4rs jar of the jassets directory in the binary.
+fn generate_embedded_jar_src(out_dir: &str) -> Result<(), J4rsBuildError> {
+    let home = env::var("CARGO_MANIFEST_DIR")?;
+    let jar_name = format!("j4rs-{}-jar-with-dependencies.jar", VERSION);
+    let jar_path = Path::new(&home).join("jassets").join(&jar_name);
+    let jar_bytes = fs::read(&jar_path)
+        .map_err(|error| J4rsBuildError { description: format!("Cannot embed the j4rs jar {}: {}", jar_path.display(), error) })?;
+    println!("cargo:rerun-if-changed={}", jar_path.display());
+
+    let dest_path = Path::new(&out_dir).join("j4rs_embedded.rs");
+    let mut f = File::create(&dest_path)?;
+    let contents = format!(
+        "
+const EMBEDDED_JAR: &[u8] = include_bytes!({:?});
+const EMBEDDED_JAR_NAME: &str = {:?};
+const EMBEDDED_JAR_SHA256: &str = \"{:x}\";
+", jar_path, jar_name, Sha256::digest(&jar_bytes));
+
+    f.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
 // Copies the jars from the `java` directory to the source directory of rust.
 fn copy_jars_from_java(jar_source_path: &str) -> Result<(), J4rsBuildError> {
     if let Ok(mut source_jar_file) = File::open(&jar_source_path) {
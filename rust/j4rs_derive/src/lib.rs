@@ -0,0 +1,316 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Derive macros for [j4rs](https://crates.io/crates/j4rs).
+//!
+//! Please use these macros via the re-exports of the j4rs crate.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Lit, Meta, NestedMeta, parse_macro_input};
+
+/// Derives `j4rs::JavaClass` for a struct with named fields.
+///
+/// Along with the `JavaClass` implementation, it generates the `TryFrom` implementations
+/// for transforming the struct to an `InvocationArg` and an `Instance` to the struct.
+///
+/// The Java class is defined with the `class` container attribute:
+///
+/// ```ignore
+/// #[derive(JavaClass)]
+/// #[j4rs(class = "com.acme.Order", rename_all = "camelCase")]
+/// struct Order {
+///     order_id: i64,
+///     #[j4rs(rename = "desc")]
+///     description: String,
+///     #[j4rs(skip)]
+///     cached: Option<String>,
+/// }
+/// ```
+///
+/// The `rename_all` may be one of `camelCase` (default), `snake_case`, `PascalCase`, `SCREAMING_SNAKE_CASE` and `lowercase`.
+#[proc_macro_derive(JavaClass, attributes(j4rs))]
+pub fn derive_java_class(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match impl_java_class(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn impl_java_class(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics, "JavaClass cannot be derived for generic structs"));
+    }
+
+    let container = ContainerAttrs::from_attrs(&input.attrs)?;
+    let class_name = match container.class_name.clone() {
+        Some(class_name) => class_name,
+        None => return Err(syn::Error::new(
+            Span::call_site(),
+            "JavaClass requires the Java class to be defined, e.g. #[j4rs(class = \"com.acme.Order\")]")),
+    };
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(syn::Error::new_spanned(ident, "JavaClass can be derived only for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(ident, "JavaClass can be derived only for structs")),
+    };
+
+    let mut java_fields = Vec::new();
+    let mut to_properties = Vec::new();
+    let mut from_properties = Vec::new();
+    for field in fields {
+        let field_ident = field.ident.as_ref().expect("named fields always have an ident");
+        let field_attrs = FieldAttrs::from_attrs(&field.attrs)?;
+        if field_attrs.skip {
+            from_properties.push(quote! {
+                #field_ident: ::std::default::Default::default()
+            });
+            continue;
+        }
+        let rust_name = field_ident.to_string().trim_start_matches("r#").to_string();
+        let java_name = field_attrs.rename.unwrap_or_else(|| container.rename_rule.apply(&rust_name));
+
+        java_fields.push(quote! {
+            ::j4rs::JavaField { rust_name: #rust_name, java_name: #java_name }
+        });
+        to_properties.push(quote! {
+            properties.insert(
+                #java_name.to_string(),
+                ::j4rs::__private::to_property(&self.#field_ident, #rust_name, #java_name, #class_name)?);
+        });
+        from_properties.push(quote! {
+            #field_ident: ::j4rs::__private::from_property(&mut properties, #rust_name, #java_name, #class_name)?
+        });
+    }
+
+    Ok(quote! {
+        impl ::j4rs::JavaClass for #ident {
+            fn java_class_name() -> &'static str {
+                #class_name
+            }
+
+            fn java_fields() -> &'static [::j4rs::JavaField] {
+                const FIELDS: &[::j4rs::JavaField] = &[#(#java_fields),*];
+                FIELDS
+            }
+
+            fn to_java_properties(&self) -> ::j4rs::errors::Result<::j4rs::__private::JavaProperties> {
+                let mut properties = ::j4rs::__private::JavaProperties::new();
+                #(#to_properties)*
+                Ok(properties)
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_java_properties(mut properties: ::j4rs::__private::JavaProperties) -> ::j4rs::errors::Result<Self> {
+                Ok(#ident {
+                    #(#from_properties),*
+                })
+            }
+        }
+
+        impl ::std::convert::TryFrom<#ident> for ::j4rs::InvocationArg {
+            type Error = ::j4rs::errors::J4RsError;
+            fn try_from(value: #ident) -> ::j4rs::errors::Result<::j4rs::InvocationArg> {
+                ::j4rs::__private::to_invocation_arg(&value)
+            }
+        }
+
+        impl<'a> ::std::convert::TryFrom<&'a #ident> for ::j4rs::InvocationArg {
+            type Error = ::j4rs::errors::J4RsError;
+            fn try_from(value: &'a #ident) -> ::j4rs::errors::Result<::j4rs::InvocationArg> {
+                ::j4rs::__private::to_invocation_arg(value)
+            }
+        }
+
+        impl ::std::convert::TryFrom<::j4rs::Instance> for #ident {
+            type Error = ::j4rs::errors::J4RsError;
+            fn try_from(instance: ::j4rs::Instance) -> ::j4rs::errors::Result<#ident> {
+                let jvm = ::j4rs::Jvm::attach_thread()?;
+                <#ident as ::j4rs::JavaClass>::from_instance(&jvm, instance)
+            }
+        }
+    })
+}
+
+struct ContainerAttrs {
+    class_name: Option<String>,
+    rename_rule: RenameRule,
+}
+
+impl ContainerAttrs {
+    fn from_attrs(attrs: &[syn::Attribute]) -> syn::Result<ContainerAttrs> {
+        let mut container = ContainerAttrs { class_name: None, rename_rule: RenameRule::Camel };
+        for meta in j4rs_metas(attrs)? {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("class") => {
+                    container.class_name = Some(lit_str(&nv.lit)?);
+                }
+                Meta::NameValue(nv) if nv.path.is_ident("rename_all") => {
+                    container.rename_rule = RenameRule::from_str(&lit_str(&nv.lit)?)
+                        .ok_or_else(|| syn::Error::new_spanned(&nv.lit, "Unknown rename_all rule. Please use one of camelCase, snake_case, PascalCase, SCREAMING_SNAKE_CASE or lowercase"))?;
+                }
+                _ => return Err(syn::Error::new_spanned(meta, "Unknown j4rs container attribute")),
+            }
+        }
+        Ok(container)
+    }
+}
+
+struct FieldAttrs {
+    rename: Option<String>,
+    skip: bool,
+}
+
+impl FieldAttrs {
+    fn from_attrs(attrs: &[syn::Attribute]) -> syn::Result<FieldAttrs> {
+        let mut field = FieldAttrs { rename: None, skip: false };
+        for meta in j4rs_metas(attrs)? {
+            match &meta {
+                Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                    field.rename = Some(lit_str(&nv.lit)?);
+                }
+                Meta::Path(path) if path.is_ident("skip") => {
+                    field.skip = true;
+                }
+                _ => return Err(syn::Error::new_spanned(meta, "Unknown j4rs field attribute")),
+            }
+        }
+        Ok(field)
+    }
+}
+
+// Collects the metas of all the #[j4rs(...)] attributes
+fn j4rs_metas(attrs: &[syn::Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("j4rs")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for nested in list.nested {
+                    match nested {
+                        NestedMeta::Meta(meta) => metas.push(meta),
+                        NestedMeta::Lit(lit) => return Err(syn::Error::new_spanned(lit, "Unexpected literal in j4rs attribute")),
+                    }
+                }
+            }
+            other => return Err(syn::Error::new_spanned(other, "Expected #[j4rs(...)]")),
+        }
+    }
+    Ok(metas)
+}
+
+fn lit_str(lit: &Lit) -> syn::Result<String> {
+    match lit {
+        Lit::Str(s) => Ok(s.value()),
+        _ => Err(syn::Error::new_spanned(lit, "Expected a string literal")),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RenameRule {
+    Camel,
+    Snake,
+    Pascal,
+    ScreamingSnake,
+    Lower,
+}
+
+impl RenameRule {
+    fn from_str(rule: &str) -> Option<RenameRule> {
+        match rule {
+            "camelCase" => Some(RenameRule::Camel),
+            "snake_case" => Some(RenameRule::Snake),
+            "PascalCase" => Some(RenameRule::Pascal),
+            "SCREAMING_SNAKE_CASE" => Some(RenameRule::ScreamingSnake),
+            "lowercase" => Some(RenameRule::Lower),
+            _ => None,
+        }
+    }
+
+    // Rust field names are expected to be in snake_case.
+    fn apply(&self, field_name: &str) -> String {
+        match self {
+            RenameRule::Snake => field_name.to_string(),
+            RenameRule::ScreamingSnake => field_name.to_uppercase(),
+            RenameRule::Lower => field_name.replace('_', ""),
+            RenameRule::Pascal => Self::capitalize_words(field_name, true),
+            RenameRule::Camel => Self::capitalize_words(field_name, false),
+        }
+    }
+
+    fn capitalize_words(field_name: &str, capitalize_first: bool) -> String {
+        let mut renamed = String::with_capacity(field_name.len());
+        let mut capitalize = capitalize_first;
+        for c in field_name.chars() {
+            if c == '_' {
+                // Leading underscores are kept
+                if renamed.is_empty() {
+                    renamed.push(c);
+                } else {
+                    capitalize = true;
+                }
+            } else if capitalize {
+                renamed.extend(c.to_uppercase());
+                capitalize = false;
+            } else {
+                renamed.push(c);
+            }
+        }
+        renamed
+    }
+}
+
+#[cfg(test)]
+mod derive_unit_tests {
+    use super::*;
+
+    #[test]
+    fn rename_rules() {
+        assert_eq!(RenameRule::Camel.apply("order_id"), "orderId");
+        assert_eq!(RenameRule::Camel.apply("id"), "id");
+        assert_eq!(RenameRule::Camel.apply("a_b_c"), "aBC");
+        assert_eq!(RenameRule::Pascal.apply("order_id"), "OrderId");
+        assert_eq!(RenameRule::Snake.apply("order_id"), "order_id");
+        assert_eq!(RenameRule::ScreamingSnake.apply("order_id"), "ORDER_ID");
+        assert_eq!(RenameRule::Lower.apply("order_id"), "orderid");
+        assert_eq!(RenameRule::from_str("kebab-case"), None);
+    }
+
+    #[test]
+    fn class_is_required() {
+        let input: DeriveInput = syn::parse_quote! {
+            struct Order {
+                order_id: i64,
+            }
+        };
+        assert!(impl_java_class(&input).is_err());
+    }
+
+    #[test]
+    fn unknown_attributes_are_rejected() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[j4rs(class = "com.acme.Order", unknown = "x")]
+            struct Order {
+                order_id: i64,
+            }
+        };
+        assert!(impl_java_class(&input).is_err());
+    }
+}
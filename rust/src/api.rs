@@ -12,11 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fs, mem};
+use std::{fmt, fs, mem};
 use std::any::Any;
+use std::ffi::{CString, NulError};
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::ops::Drop;
-use std::os::raw::c_void;
+use std::os::raw::{c_char, c_void};
 use std::path::{Path, PathBuf};
 use std::ptr;
 use std::sync::mpsc::{channel, Receiver, Sender};
@@ -35,7 +37,6 @@ use jni_sys::{
     JNI_EVERSION,
     JNI_OK,
     JNI_TRUE,
-    JNI_VERSION_1_8,
     JNIEnv,
     jobject,
     jsize,
@@ -46,12 +47,17 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json;
 
-use crate::{api_tweaks as tweaks, MavenSettings, cache};
+use crate::{api_tweaks as tweaks, AttachOptions, JvmHandle, MavenSettings, Monitor, cache};
+use crate::api_tweaks::JvmLibLocation;
+use crate::config::JvmConfig;
+use crate::diagnostics;
 use crate::errors;
 use crate::errors::{J4RsError, opt_to_res};
+use crate::java_version::{JavaVersion, JniVersion};
 use crate::jni_utils;
-use crate::provisioning::{get_maven_settings, JavaArtifact, LocalJarArtifact, MavenArtifact};
+use crate::provisioning::{get_maven_settings, JavaArtifact, LocalJarArtifact, MavenArtifact, MavenArtifactRepo, MavenDependency};
 use crate::provisioning;
+use crate::task::RustTask;
 use crate::utils;
 
 use super::logger::{debug, error, info, warn};
@@ -73,12 +79,19 @@ pub struct Jvm {
 impl Jvm {
     /// Creates a new Jvm.
     pub fn new(jvm_options: &[String], lib_name_to_load: Option<String>) -> errors::Result<Jvm> {
-        Self::create_jvm(jvm_options, lib_name_to_load)
+        Self::create_jvm(jvm_options, lib_name_to_load, &AttachOptions::default(), None, JniVersion::default()).map(|(jvm, _)| jvm)
     }
 
     /// Attaches the current thread to an active JavaVM
     pub fn attach_thread() -> errors::Result<Jvm> {
-        Self::create_jvm(&[], None)
+        Self::create_jvm(&[], None, &AttachOptions::default(), None, JniVersion::default()).map(|(jvm, _)| jvm)
+    }
+
+    /// Attaches the current thread to an active JavaVM, using the provided `AttachOptions`.
+    ///
+    /// The options have no effect if the current thread is already attached.
+    pub fn attach_thread_with_options(attach_options: &AttachOptions) -> errors::Result<Jvm> {
+        Self::create_jvm(&[], None, attach_options, None, JniVersion::default()).map(|(jvm, _)| jvm)
     }
 
     /// If false, the thread will not be detached when the Jvm is being dropped.
@@ -92,13 +105,17 @@ impl Jvm {
 
     /// Creates a new Jvm.
     /// If a JavaVM is already created by the current process, it attempts to attach the current thread to it.
-    fn create_jvm(jvm_options: &[String], lib_name_to_load: Option<String>) -> errors::Result<Jvm> {
+    ///
+    /// Along with the Jvm, it returns whether a new JavaVM was created.
+    fn create_jvm(jvm_options: &[String], lib_name_to_load: Option<String>, attach_options: &AttachOptions, jvm_lib: Option<&JvmLibLocation>, jni_version: JniVersion) -> errors::Result<(Jvm, bool)> {
+        cache::ensure_jvm_not_destroyed()?;
         debug("Creating a Jvm");
         let mut jvm: *mut JavaVM = ptr::null_mut();
         let mut jni_environment: *mut JNIEnv = ptr::null_mut();
 
         // Create the Jvm atomically
         let _g = cache::MUTEX.lock()?;
+        let mut created = false;
 
         let result = if let Some(env) = cache::get_thread_local_env_opt() {
             info("A JVM is already created for this thread. Retrieving it...");
@@ -106,7 +123,8 @@ impl Jvm {
 
             JNI_OK
         } else {
-            let created_vm = Self::get_created_vm();
+            tweaks::load_jvm_lib(jvm_lib)?;
+            let created_vm = Self::get_created_vm(attach_options);
 
             let res_int = if created_vm.is_some() {
                 debug("A JVM is already created by another thread. Retrieving it...");
@@ -115,21 +133,24 @@ impl Jvm {
                 JNI_OK
             } else {
                 info("No JVMs exist. Creating a new one...");
-                let mut jvm_options_vec: Vec<JavaVMOption> = jvm_options
+                created = true;
+                // The C Strings need to live until the JVM is created
+                let jvm_options_cstrings = jvm_options
                     .iter()
-                    .map(|opt| {
-                        let cstr = utils::to_c_string(opt);
-                        let jo = JavaVMOption {
-                            optionString: utils::to_c_string(opt),
+                    .map(|opt| CString::new(opt.as_str()))
+                    .collect::<Result<Vec<CString>, NulError>>()?;
+                let mut jvm_options_vec: Vec<JavaVMOption> = jvm_options_cstrings
+                    .iter()
+                    .map(|cstr| {
+                        JavaVMOption {
+                            optionString: cstr.as_ptr() as *mut c_char,
                             extraInfo: ptr::null_mut() as *mut c_void,
-                        };
-                        utils::drop_c_string(cstr);
-                        jo
+                        }
                     })
                     .collect();
 
                 let mut jvm_arguments = JavaVMInitArgs {
-                    version: JNI_VERSION_1_8,
+                    version: jni_version.as_jint(),
                     nOptions: jvm_options.len() as i32,
                     options: jvm_options_vec.as_mut_ptr(),
                     ignoreUnrecognized: JNI_TRUE,
@@ -147,18 +168,21 @@ impl Jvm {
 
         if result != JNI_OK {
             let error_message = match result {
-                JNI_EDETACHED => "thread detached from the JVM",
-                JNI_EEXIST => "JVM already created",
-                JNI_EINVAL => "invalid arguments",
-                JNI_ENOMEM => "not enough memory",
-                JNI_ERR => "unknown error",
-                JNI_EVERSION => "JNI version error",
-                _ => "unknown JNI error value",
+                JNI_EDETACHED => "thread detached from the JVM".to_string(),
+                JNI_EEXIST => "JVM already created".to_string(),
+                JNI_EINVAL => "invalid arguments".to_string(),
+                JNI_ENOMEM => "not enough memory".to_string(),
+                JNI_ERR => "unknown error".to_string(),
+                JNI_EVERSION => format!("JNI version error: the JVM does not support the requested JNI version {}", jni_version),
+                _ => "unknown JNI error value".to_string(),
             };
 
             Err(errors::J4RsError::JavaError(format!("Could not create the JVM: {}", error_message).to_string()))
         } else {
             let jvm = Self::try_from(jni_environment)?;
+            if created {
+                *cache::NATIVE_LIB_NAME.lock()? = lib_name_to_load.clone();
+            }
             if let Some(libname) = lib_name_to_load {
                 // Pass to the Java world the name of the j4rs library.
                 debug(&format!("Initializing NativeCallbackSupport with libname {}", libname));
@@ -168,19 +192,21 @@ impl Jvm {
                 debug("NativeCallbackSupport initialized");
             }
 
-            Ok(jvm)
+            Ok((jvm, created))
         }
     }
 
     pub fn try_from(jni_environment: *mut JNIEnv) -> errors::Result<Jvm> {
+        cache::ensure_jvm_not_destroyed()?;
         unsafe {
             let _ = cache::get_jni_get_method_id().or_else(|| cache::set_jni_get_method_id((**jni_environment).GetMethodID));
             let _ = cache::get_jni_get_static_method_id().or_else(|| cache::set_jni_get_static_method_id((**jni_environment).GetStaticMethodID));
             let _ = cache::get_jni_new_object().or_else(|| cache::set_jni_new_object((**jni_environment).NewObject));
-            let _ = cache::get_jni_new_string_utf().or_else(|| cache::set_jni_new_string_utf((**jni_environment).NewStringUTF));
-            let _ = cache::get_jni_get_string_utf_chars().or_else(|| cache::set_jni_get_string_utf_chars((**jni_environment).GetStringUTFChars));
-            let _ = cache::get_jni_release_string_utf_chars().or_else(|| cache::set_jni_release_string_utf_chars((**jni_environment).ReleaseStringUTFChars));
+            let _ = cache::get_jni_new_string().or_else(|| cache::set_jni_new_string((**jni_environment).NewString));
+            let _ = cache::get_jni_get_string_length().or_else(|| cache::set_jni_get_string_length((**jni_environment).GetStringLength));
+            let _ = cache::get_jni_get_string_region().or_else(|| cache::set_jni_get_string_region((**jni_environment).GetStringRegion));
             let _ = cache::get_jni_call_object_method().or_else(|| cache::set_jni_call_object_method((**jni_environment).CallObjectMethod));
+            let _ = cache::get_jni_call_boolean_method().or_else(|| cache::set_jni_call_boolean_method((**jni_environment).CallBooleanMethod));
             let _ = cache::get_jni_call_void_method().or_else(|| cache::set_jni_call_void_method((**jni_environment).CallVoidMethod));
             let _ = cache::get_jni_call_static_object_method().or_else(|| cache::set_jni_call_static_object_method((**jni_environment).CallStaticObjectMethod));
             let _ = cache::get_jni_new_object_array().or_else(|| cache::set_jni_new_object_array((**jni_environment).NewObjectArray));
@@ -191,6 +217,11 @@ impl Jvm {
             let _ = cache::get_jni_delete_local_ref().or_else(|| cache::set_jni_delete_local_ref((**jni_environment).DeleteLocalRef));
             let _ = cache::get_jni_delete_global_ref().or_else(|| cache::set_jni_delete_global_ref((**jni_environment).DeleteGlobalRef));
             let _ = cache::get_jni_new_global_ref().or_else(|| cache::set_jni_new_global_ref((**jni_environment).NewGlobalRef));
+            let _ = cache::get_jni_push_local_frame().or_else(|| cache::set_jni_push_local_frame((**jni_environment).PushLocalFrame));
+            let _ = cache::get_jni_pop_local_frame().or_else(|| cache::set_jni_pop_local_frame((**jni_environment).PopLocalFrame));
+            let _ = cache::get_jni_ensure_local_capacity().or_else(|| cache::set_jni_ensure_local_capacity((**jni_environment).EnsureLocalCapacity));
+            let _ = cache::get_jni_monitor_enter().or_else(|| cache::set_jni_monitor_enter((**jni_environment).MonitorEnter));
+            let _ = cache::get_jni_monitor_exit().or_else(|| cache::set_jni_monitor_exit((**jni_environment).MonitorExit));
 
             match (ec, ed, exclear) {
                 (Some(ec), Some(ed), Some(exclear)) => {
@@ -208,6 +239,8 @@ impl Jvm {
                             cache::set_thread_local_env(Some(jni_environment));
                         }
                         cache::add_active_jvm();
+                        // Release the references of the Instances that were dropped by threads without a JNIEnv
+                        jni_utils::release_deferred_java_refs(jni_environment);
 
                         Ok(jvm)
                     }
@@ -220,7 +253,9 @@ impl Jvm {
     }
 
     /// Creates an `Instance` of the class `class_name`, passing an array of `InvocationArg`s to construct the instance.
+    #[track_caller]
     pub fn create_instance(&self, class_name: &str, inv_args: &[InvocationArg]) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Instantiating class {} using {} arguments", class_name, inv_args.len()));
         unsafe {
             // Factory invocation - first argument: create a jstring to pass as argument for the class_name
@@ -274,15 +309,14 @@ impl Jvm {
             }
 
             // Create and return the Instance
-            Self::do_return(self.jni_env, Instance {
-                jinstance: native_invocation_global_instance,
-                class_name: class_name.to_string(),
-            })
+            Self::do_return(self.jni_env, Instance::new(native_invocation_global_instance, class_name))
         }
     }
 
     /// Retrieves the static class `class_name`.
+    #[track_caller]
     pub fn static_class(&self, class_name: &str) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Retrieving static class {}", class_name));
         unsafe {
             // Factory invocation - first argument: create a jstring to pass as argument for the class_name
@@ -307,7 +341,9 @@ impl Jvm {
     /// Creates a new Java Array with elements of the class `class_name`.
     /// The array will have the `InvocationArg`s populated.
     /// The `InvocationArg`s __must__ be of type _class_name_.
+    #[track_caller]
     pub fn create_java_array(&self, class_name: &str, inv_args: &[InvocationArg]) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Creating a java array of class {} with {} elements", class_name, inv_args.len()));
         unsafe {
             // Factory invocation - first argument: create a jstring to pass as argument for the class_name
@@ -361,20 +397,20 @@ impl Jvm {
             jni_utils::delete_java_ref(self.jni_env, class_name_jstring);
 
             // Create and return the Instance
-            Self::do_return(self.jni_env, Instance {
-                jinstance: native_invocation_global_instance,
-                class_name: class_name.to_string(),
-            })
+            Self::do_return(self.jni_env, Instance::new(native_invocation_global_instance, class_name))
         }
     }
 
     /// Creates a new Java List with elements of the class `class_name`.
     /// The array will have the `InvocationArg`s populated.
     /// The `InvocationArg`s __must__ be of type _class_name_.
+    #[track_caller]
     pub fn create_java_list(&self, class_name: &str, inv_args: &[InvocationArg]) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         Jvm::do_create_java_list(self.jni_env, class_name, inv_args)
     }
 
+    #[track_caller]
     fn do_create_java_list(jni_env: *mut JNIEnv, class_name: &str, inv_args: &[InvocationArg]) -> errors::Result<Instance> {
         debug(&format!("Creating a java list of class {} with {} elements", class_name, inv_args.len()));
         unsafe {
@@ -429,15 +465,14 @@ impl Jvm {
             jni_utils::delete_java_ref(jni_env, class_name_jstring);
 
             // Create and return the Instance
-            Self::do_return(jni_env, Instance {
-                jinstance: native_invocation_global_instance,
-                class_name: class_name.to_string(),
-            })
+            Self::do_return(jni_env, Instance::new(native_invocation_global_instance, class_name))
         }
     }
 
     /// Invokes the method `method_name` of a created `Instance`, passing an array of `InvocationArg`s. It returns an `Instance` as the result of the invocation.
+    #[track_caller]
     pub fn invoke(&self, instance: &Instance, method_name: &str, inv_args: &[InvocationArg]) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Invoking method {} of class {} using {} arguments", method_name, instance.class_name, inv_args.len()));
         unsafe {
             // First argument: create a jstring to pass as argument for the method_name
@@ -491,15 +526,14 @@ impl Jvm {
             jni_utils::delete_java_ref(self.jni_env, method_name_jstring);
 
             // Create and return the Instance
-            Self::do_return(self.jni_env, Instance {
-                jinstance: native_invocation_global_instance,
-                class_name: cache::UNKNOWN_FOR_RUST.to_string(),
-            })
+            Self::do_return(self.jni_env, Instance::new(native_invocation_global_instance, cache::UNKNOWN_FOR_RUST))
         }
     }
 
     /// Retrieves the field `field_name` of a created `Instance`.
+    #[track_caller]
     pub fn field(&self, instance: &Instance, field_name: &str) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Retrieving field {} of class {}", field_name, instance.class_name));
         unsafe {
             // First argument: create a jstring to pass as argument for the field_name
@@ -521,16 +555,15 @@ impl Jvm {
             jni_utils::delete_java_ref(self.jni_env, field_name_jstring);
 
             // Create and return the Instance
-            Self::do_return(self.jni_env, Instance {
-                jinstance: native_invocation_global_instance,
-                class_name: cache::UNKNOWN_FOR_RUST.to_string(),
-            })
+            Self::do_return(self.jni_env, Instance::new(native_invocation_global_instance, cache::UNKNOWN_FOR_RUST))
         }
     }
 
     /// Invokes the method `method_name` of a created `Instance`, passing an array of `InvocationArg`s.
     /// It returns a Result of `InstanceReceiver` that may be used to get an underlying `Receiver<Instance>`. The result of the invocation will come via this Receiver.
+    #[track_caller]
     pub fn invoke_to_channel(&self, instance: &Instance, method_name: &str, inv_args: &[InvocationArg]) -> errors::Result<InstanceReceiver> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Invoking method {} of class {} using {} arguments. The result of the invocation will come via an InstanceReceiver", method_name, instance.class_name, inv_args.len()));
         unsafe {
             // Create the channel
@@ -593,11 +626,13 @@ impl Jvm {
             jni_utils::delete_java_ref(self.jni_env, method_name_jstring);
 
             // Create and return the Instance
-            Self::do_return(self.jni_env, InstanceReceiver::new(rx, address))
+            Self::do_return(self.jni_env, InstanceReceiver::new(rx, address, &instance.class_name))
         }
     }
 
+    #[track_caller]
     pub fn init_callback_channel(&self, instance: &Instance) -> errors::Result<InstanceReceiver> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Initializing callback channel"));
         unsafe {
             // Create the channel
@@ -618,12 +653,14 @@ impl Jvm {
             );
 
             // Create and return the Instance
-            Self::do_return(self.jni_env, InstanceReceiver::new(rx, address))
+            Self::do_return(self.jni_env, InstanceReceiver::new(rx, address, &instance.class_name))
         }
     }
 
     /// Invokes the static method `method_name` of the class `class_name`, passing an array of `InvocationArg`s. It returns an `Instance` as the result of the invocation.
+    #[track_caller]
     pub fn invoke_static(&self, class_name: &str, method_name: &str, inv_args: &[InvocationArg]) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Invoking static method {} of class {} using {} arguments", method_name, class_name, inv_args.len()));
         unsafe {
             // Factory invocation - first argument: create a jstring to pass as argument for the class_name
@@ -689,7 +726,9 @@ impl Jvm {
     }
 
     /// Creates a clone of the provided Instance
+    #[track_caller]
     pub fn clone_instance(&self, instance: &Instance) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         unsafe {
             // Call the clone method
             let native_invocation_instance = (opt_to_res(cache::get_jni_call_static_object_method())?)(
@@ -705,7 +744,9 @@ impl Jvm {
     }
 
     /// Invokes the static method `method_name` of the class `class_name`, passing an array of `InvocationArg`s. It returns an `Instance` as the result of the invocation.
+    #[track_caller]
     pub fn cast(&self, from_instance: &Instance, to_class: &str) -> errors::Result<Instance> {
+        cache::ensure_jvm_not_destroyed()?;
         debug(&format!("Casting to class {}", to_class));
         unsafe {
             // First argument is the jobject that is inside the from_instance
@@ -733,7 +774,10 @@ impl Jvm {
     }
 
     /// Returns the Rust representation of the provided instance
+    ///
+    /// A Java `null`, as well as an empty `java.util.Optional`, is transformed to `None` when `T` is an `Option`.
     pub fn to_rust<T>(&self, instance: Instance) -> errors::Result<T> where T: DeserializeOwned {
+        cache::ensure_jvm_not_destroyed()?;
         unsafe {
             debug("Invoking the getJson method");
             // Call the getJson method. This returns a localref
@@ -777,10 +821,16 @@ impl Jvm {
     ///
     /// This is useful for build scripts that need jars for the runtime that can be downloaded from e.g. Maven.
     ///
-    /// The function deploys __only__ the specified artifact, not its transitive dependencies.
+    /// The function deploys __only__ the specified artifact, not its transitive dependencies, unless
+    /// the artifact is a `MavenArtifact` created with `with_dependencies`. In that case, the whole dependency tree
+    /// is resolved and deployed, and a failure to resolve or fetch any of the artifacts is returned as an error.
     pub fn deploy_artifact<T: Any + JavaArtifact>(&self, artifact: &T) -> errors::Result<()> {
         let artifact = artifact as &dyn Any;
-        if let Some(maven_artifact) = artifact.downcast_ref::<MavenArtifact>() {
+        if let Some(maven_artifact) = artifact.downcast_ref::<MavenArtifact>().filter(|artifact| artifact.with_dependencies) {
+            let tree = self.invoke_maven_dependency_resolver(maven_artifact, "deploy")?;
+            debug(&format!("Deployed the Maven dependency tree\n{}", tree));
+            Ok(())
+        } else if let Some(maven_artifact) = artifact.downcast_ref::<MavenArtifact>() {
             for repo in get_maven_settings().repos.into_iter() {
                 let instance = self.create_instance(
                     "org.astonbitecode.j4rs.api.deploy.SimpleMavenDeployer",
@@ -818,6 +868,102 @@ impl Jvm {
         }
     }
 
+    /// Resolves the transitive dependencies of a Maven artifact, without deploying anything.
+    ///
+    /// The POMs are fetched from the repositories of the `MavenSettings`, which are searched in order.
+    /// The resolution follows the rules of Maven:
+    ///
+    /// * POMs inherit the properties, the managed dependencies and the dependencies of their parent POMs.
+    /// * Missing versions and scopes are taken from the `dependencyManagement`, including imported BOMs.
+    ///   The `dependencyManagement` of the root artifact applies to the whole tree.
+    /// * Dependencies with `test`, `provided` or `system` scope, as well as optional dependencies, are not included.
+    /// * Exclusions apply to the whole subtree of the dependency that declares them.
+    /// * When more versions of an artifact are found, the nearest to the root wins. For equal depths, the one
+    ///   that is declared first wins.
+    ///
+    /// The returned tree can be printed, similarly to `mvn dependency:tree`.
+    pub fn resolve_maven_dependencies(&self, artifact: &MavenArtifact) -> errors::Result<MavenDependency> {
+        self.invoke_maven_dependency_resolver(artifact, "resolve")
+    }
+
+    fn invoke_maven_dependency_resolver(&self, artifact: &MavenArtifact, method: &str) -> errors::Result<MavenDependency> {
+        let resolver = self.create_instance(
+            "org.astonbitecode.j4rs.api.deploy.MavenDependencyResolver",
+            &[InvocationArg::try_from(&artifact.base)?])?;
+        for repo in get_maven_settings().repos.into_iter() {
+            self.invoke(&resolver, "addRepository", &[InvocationArg::try_from(repo.uri)?])?;
+        }
+        let tree = self.invoke(
+            &resolver,
+            method,
+            &[
+                InvocationArg::try_from(&artifact.group)?,
+                InvocationArg::try_from(&artifact.id)?,
+                InvocationArg::try_from(&artifact.version)?,
+                InvocationArg::try_from(&artifact.qualifier)?]);
+        match tree {
+            Ok(tree) => self.to_rust(tree),
+            // The resolver keeps the message of the failure, which is more useful than the generic Java error
+            Err(error) => match self.to_rust::<Option<String>>(self.invoke(&resolver, "getLastError", &[])?)? {
+                Some(last_error) => Err(errors::J4RsError::GeneralError(format!(
+                    "Could not {} the Maven dependencies of {}:{}:{}: {}", method, artifact.group, artifact.id, artifact.version, last_error))),
+                None => Err(error),
+            },
+        }
+    }
+
+    /// Returns the entries of the `java.class.path` of the running Java VM.
+    pub fn classpath(&self) -> errors::Result<Vec<String>> {
+        let classpath: String = self.to_rust(self.invoke_static("java.lang.System", "getProperty", &[InvocationArg::try_from("java.class.path")?])?)?;
+        Ok(classpath.split(utils::classpath_sep()).map(|entry| entry.to_string()).collect())
+    }
+
+    /// Returns the version of the Java runtime.
+    pub fn java_version(&self) -> errors::Result<JavaVersion> {
+        let version = self.system_property("java.version")?.unwrap_or_default();
+        let vendor = self.system_property("java.vendor")?.unwrap_or_default();
+        JavaVersion::parse(&version, &vendor)
+    }
+
+    /// Returns the value of a Java system property, or `None` if the property is not set.
+    pub fn system_property(&self, key: &str) -> errors::Result<Option<String>> {
+        self.to_rust(self.invoke_static("java.lang.System", "getProperty", &[InvocationArg::try_from(key)?])?)
+    }
+
+    /// Sets a Java system property and returns its previous value.
+    pub fn set_system_property(&self, key: &str, value: &str) -> errors::Result<Option<String>> {
+        self.to_rust(self.invoke_static(
+            "java.lang.System",
+            "setProperty",
+            &[InvocationArg::try_from(key)?, InvocationArg::try_from(value)?])?)
+    }
+
+    /// Removes a Java system property and returns its previous value.
+    pub fn clear_system_property(&self, key: &str) -> errors::Result<Option<String>> {
+        self.to_rust(self.invoke_static("java.lang.System", "clearProperty", &[InvocationArg::try_from(key)?])?)
+    }
+
+    /// Returns the sorted names of the Java system properties.
+    pub fn system_property_names(&self) -> errors::Result<Vec<String>> {
+        let properties = self.invoke_static("java.lang.System", "getProperties", &[])?;
+        let mut names: Vec<String> = self.to_rust(self.invoke(&properties, "stringPropertyNames", &[])?)?;
+        names.sort();
+        Ok(names)
+    }
+
+    /// Returns the Java system properties.
+    ///
+    /// Properties with values that are not strings are left out.
+    pub fn system_properties(&self) -> errors::Result<HashMap<String, String>> {
+        let properties: HashMap<String, serde_json::Value> = self.to_rust(self.invoke_static("java.lang.System", "getProperties", &[])?)?;
+        Ok(properties.into_iter()
+            .filter_map(|(key, value)| match value {
+                serde_json::Value::String(value) => Some((key, value)),
+                _ => None,
+            })
+            .collect())
+    }
+
     /// Copies the jassets default directory and the j4rs dynamic library under the specified location.
     /// This is useful for cases when `with_base_path` method is used when building a Jvm with the JvmBuilder.
     /// Build scripts should use this method.
@@ -847,6 +993,128 @@ impl Jvm {
         ChainableInstance::new(instance, &self)
     }
 
+    /// Returns a thread-safe handle of the Java VM that this `Jvm` uses.
+    ///
+    /// The handle can be shared between threads, in order for each thread to retrieve its own `Jvm`.
+    pub fn handle(&self) -> errors::Result<JvmHandle> {
+        cache::ensure_jvm_not_destroyed()?;
+        JvmHandle::from_env(self.jni_env)
+    }
+
+    /// Destroys the JVM of the process, via the JNI `DestroyJavaVM`.
+    ///
+    /// It waits for the non-daemon Java threads to finish and runs the Java shutdown hooks.
+    /// Afterwards, using any `Jvm` or `Instance` returns an error. A new JVM cannot be created by the same process.
+    pub fn destroy(self) -> errors::Result<()> {
+        cache::ensure_jvm_not_destroyed()?;
+        unsafe {
+            let mut java_vm: *mut JavaVM = ptr::null_mut();
+            let res = (opt_to_res((**self.jni_env).GetJavaVM)?)(self.jni_env, &mut java_vm);
+            if res != JNI_OK || java_vm.is_null() {
+                return Err(errors::J4RsError::JniError(format!("Could not retrieve the Java VM to destroy: {}", res)));
+            }
+            info("Destroying the JVM");
+            let res = (opt_to_res((**java_vm).DestroyJavaVM)?)(java_vm);
+            if res == JNI_OK {
+                cache::set_jvm_destroyed();
+                Ok(())
+            } else {
+                Err(errors::J4RsError::JniError(format!("Could not destroy the JVM: {}", res)))
+            }
+        }
+    }
+
+    /// Returns the number of the Java references that were released with a delay since the process started.
+    ///
+    /// When an `Instance` is dropped by a thread that is not attached to the JVM, its Java reference cannot be released
+    /// immediately. It is rather released the next time an attached thread uses j4rs, or by the reaper thread
+    /// that is started with `JvmHandle::start_reference_reaper`.
+    pub fn deferred_reference_releases() -> usize {
+        jni_utils::deferred_java_ref_releases()
+    }
+
+    /// Returns the number of the Java references that are waiting for a delayed release.
+    ///
+    /// See `Jvm::deferred_reference_releases`.
+    pub fn pending_reference_releases() -> usize {
+        jni_utils::pending_deferred_java_ref_releases()
+    }
+
+    /// Creates a lazy iterator over the elements of an `Instance` that contains
+    /// a Java `Iterable`, `Iterator`, `Enumeration`, `java.util.stream.Stream` or array.
+    ///
+    /// The elements are retrieved from the Java world while iterating.
+    pub fn iterate(&self, instance: &Instance) -> errors::Result<InstanceIterator<'_>> {
+        let to_iterate = InvocationArg::from(self.clone_instance(instance)?);
+        let batch_iterator = self.invoke_static(cache::BATCH_ITERATOR, "of", &[to_iterate])?;
+        Ok(InstanceIterator::new(batch_iterator, self))
+    }
+
+    /// Creates a `java.lang.Runnable` Instance that executes the provided closure when it is run.
+    ///
+    /// The closure may be executed by any Java thread, for example by the threads of a Java `ExecutorService`, and possibly many times.
+    /// Errors and panics of the closure are thrown to Java as `InvocationException`s. The closure is dropped
+    /// when the Runnable is closed, after casting it to `java.lang.AutoCloseable`, or after it is garbage collected.
+    ///
+    /// _Note: The j4rs native library needs to be loaded by Java. This is done by default while building the Jvm._
+    #[track_caller]
+    pub fn create_runnable<F>(&self, f: F) -> errors::Result<Instance>
+        where F: Fn(&Jvm) -> errors::Result<()> + Send + Sync + 'static {
+        RustTask::create_instance(self, Box::new(move |jvm| f(jvm).map(|_| None)), "java.lang.Runnable")
+    }
+
+    /// Creates a `java.util.concurrent.Callable` Instance that executes the provided closure when it is called.
+    ///
+    /// The `InvocationArg` that the closure returns is the result of the `call` in the Java world. For example, the result
+    /// of the `Future` that is returned by a Java `ExecutorService` when submitting the Callable.
+    ///
+    /// The closure is executed like in `create_runnable`.
+    #[track_caller]
+    pub fn create_callable<F>(&self, f: F) -> errors::Result<Instance>
+        where F: Fn(&Jvm) -> errors::Result<InvocationArg> + Send + Sync + 'static {
+        RustTask::create_instance(self, Box::new(move |jvm| f(jvm).map(Some)), "java.util.concurrent.Callable")
+    }
+
+    /// Executes the provided function while holding the monitor of the Java object of `instance`,
+    /// like a Java `synchronized` block.
+    ///
+    /// The monitor is exited when the function returns, even if it fails or panics.
+    /// The `Monitor` that is passed to the function may be used to `wait` and `notify` on the object.
+    pub fn synchronized<T, F>(&self, instance: &Instance, f: F) -> errors::Result<T>
+        where F: FnOnce(&Monitor) -> errors::Result<T> {
+        cache::ensure_jvm_not_destroyed()?;
+        let monitor = Monitor::enter(self, instance)?;
+        f(&monitor)
+    }
+
+    /// Ensures that at least `capacity` local references can be created in the current thread.
+    pub fn ensure_local_capacity(&self, capacity: i32) -> errors::Result<()> {
+        cache::ensure_jvm_not_destroyed()?;
+        unsafe {
+            let res = (opt_to_res(cache::get_jni_ensure_local_capacity())?)(self.jni_env, capacity as jint);
+            // On failure, an OutOfMemoryError is thrown
+            Self::do_return(self.jni_env, ())?;
+            if res == JNI_OK {
+                Ok(())
+            } else {
+                Err(errors::J4RsError::JniError(format!("Could not ensure the capacity of {} local references: {}", capacity, res)))
+            }
+        }
+    }
+
+    /// Executes the provided function inside a new local reference frame, which can hold at least `capacity` local references.
+    ///
+    /// All the local references that are created while the function executes are freed when the function returns,
+    /// even if it panics. This bounds the local references that are used by loops in native methods that are called by Java.
+    ///
+    /// The result of the function is promoted out of the frame: the `Instance`s that it contains (e.g. a `Vec<Instance>`)
+    /// remain valid after the frame is popped, as they hold global references.
+    pub fn with_local_frame<T, F>(&self, capacity: i32, f: F) -> errors::Result<T>
+        where F: FnOnce(&Jvm) -> errors::Result<T> {
+        let _frame = LocalFrame::push(self.jni_env, capacity)?;
+        f(self)
+    }
+
     pub(crate) fn do_return<T>(jni_env: *mut JNIEnv, to_return: T) -> errors::Result<T> {
         unsafe {
             if (opt_to_res(cache::get_jni_exception_check())?)(jni_env) == JNI_TRUE {
@@ -860,41 +1128,31 @@ impl Jvm {
     }
 
     // Retrieves a JNIEnv in the case that a JVM is already created even from another thread.
-    fn get_created_vm() -> Option<*mut JNIEnv> {
-        unsafe {
-            // Get the number of the already created VMs. This is most probably 1, but we retrieve the number just in case...
-            let mut created_vms_size: jsize = 0;
-            tweaks::get_created_java_vms(&mut Vec::with_capacity(created_vms_size as usize), 0, &mut created_vms_size);
-
-            if created_vms_size == 0 {
-                None
-            } else {
-                debug(&format!("Retrieving the first of {} created JVMs", created_vms_size));
-                // Get the created VM (use 2 just in case... :) )
-                let mut buffer: Vec<*mut JavaVM> = Vec::with_capacity(2);
-                for _ in 0..created_vms_size { buffer.push(ptr::null_mut()); }
+    fn get_created_vm(attach_options: &AttachOptions) -> Option<*mut JNIEnv> {
+        // Get the number of the already created VMs. This is most probably 1, but we retrieve the number just in case...
+        let mut created_vms_size: jsize = 0;
+        tweaks::get_created_java_vms(&mut Vec::with_capacity(created_vms_size as usize), 0, &mut created_vms_size);
 
-                let retjint = tweaks::get_created_java_vms(&mut buffer, created_vms_size, &mut created_vms_size);
-                if retjint == JNI_OK {
-                    match (**buffer[0]).AttachCurrentThread {
-                        Some(act) => {
-                            let mut jni_environment: *mut JNIEnv = ptr::null_mut();
-                            (act)(
-                                buffer[0],
-                                (&mut jni_environment as *mut *mut JNIEnv) as *mut *mut c_void,
-                                ptr::null_mut(),
-                            );
-                            Some(jni_environment)
-                        }
-                        None => {
-                            error("Cannot attach the thread to the JVM");
-                            None
-                        }
+        if created_vms_size == 0 {
+            None
+        } else {
+            debug(&format!("Retrieving the first of {} created JVMs", created_vms_size));
+            // Get the created VM (use 2 just in case... :) )
+            let mut buffer: Vec<*mut JavaVM> = Vec::with_capacity(2);
+            for _ in 0..created_vms_size { buffer.push(ptr::null_mut()); }
+
+            let retjint = tweaks::get_created_java_vms(&mut buffer, created_vms_size, &mut created_vms_size);
+            if retjint == JNI_OK {
+                match attach_options.attach(buffer[0]) {
+                    Ok(jni_environment) => Some(jni_environment),
+                    Err(err) => {
+                        error(&format!("Cannot attach the thread to the JVM: {}", err));
+                        None
                     }
-                } else {
-                    error(&format!("Error while retrieving the created JVMs: {}", retjint));
-                    None
                 }
+            } else {
+                error(&format!("Error while retrieving the created JVMs: {}", retjint));
+                None
             }
         }
     }
@@ -931,7 +1189,8 @@ impl Jvm {
 impl Drop for Jvm {
     fn drop(&mut self) {
         if cache::remove_active_jvm() <= 0 {
-            if self.detach_thread_on_drop {
+            // A destroyed JVM has already detached the thread
+            if self.detach_thread_on_drop && !cache::is_jvm_destroyed() {
                 self.detach_current_thread();
             }
             cache::set_thread_local_env(None);
@@ -939,21 +1198,71 @@ impl Drop for Jvm {
     }
 }
 
-/// A builder for Jvm
-pub struct JvmBuilder<'a> {
-    classpath_entries: Vec<ClasspathEntry<'a>>,
-    java_opts: Vec<JavaOpt<'a>>,
+// A local reference frame that is popped when dropped.
+struct LocalFrame {
+    jni_env: *mut JNIEnv,
+}
+
+impl LocalFrame {
+    fn push(jni_env: *mut JNIEnv, capacity: i32) -> errors::Result<LocalFrame> {
+        cache::ensure_jvm_not_destroyed()?;
+        unsafe {
+            let res = (opt_to_res(cache::get_jni_push_local_frame())?)(jni_env, capacity as jint);
+            // On failure, an OutOfMemoryError is thrown
+            Jvm::do_return(jni_env, ())?;
+            if res == JNI_OK {
+                Ok(LocalFrame { jni_env })
+            } else {
+                Err(errors::J4RsError::JniError(format!("Could not push a local frame with capacity {}: {}", capacity, res)))
+            }
+        }
+    }
+}
+
+impl Drop for LocalFrame {
+    fn drop(&mut self) {
+        match cache::get_jni_pop_local_frame() {
+            Some(plf) => unsafe {
+                (plf)(self.jni_env, ptr::null_mut());
+            },
+            None => warn("Could not pop the local frame"),
+        }
+    }
+}
+
+/// A builder for Jvm.
+///
+/// The builder owns its settings: it can be assembled dynamically, cloned, stored and reused to build `Jvm`s
+/// on several threads. Its serialized form describes the settings, e.g. for diagnostics.
+#[derive(Debug, Clone, Serialize)]
+pub struct JvmBuilder {
+    classpath_entries: Vec<ClasspathEntry>,
+    java_opts: Vec<JavaOpt>,
     no_implicit_classpath: bool,
     detach_thread_on_drop: bool,
     lib_name_opt: Option<String>,
     skip_setting_native_lib: bool,
     base_path: Option<String>,
     maven_settings: MavenSettings,
+    attach_options: AttachOptions,
+    fail_on_ignored_options: bool,
+    fail_on_missing_classpath_entries: bool,
+    system_properties: Vec<(String, String)>,
+    agents: Vec<Agent>,
+    module_path_entries: Vec<String>,
+    add_modules: Vec<String>,
+    add_opens: Vec<String>,
+    add_exports: Vec<String>,
+    min_java_version: Option<u32>,
+    jni_version: JniVersion,
+    config_file: Option<PathBuf>,
+    env_config: bool,
+    jvm_lib: Option<JvmLibLocation>,
 }
 
-impl<'a> JvmBuilder<'a> {
+impl JvmBuilder {
     /// Creates a new JvmBuilder.
-    pub fn new<'b>() -> JvmBuilder<'b> {
+    pub fn new() -> JvmBuilder {
         JvmBuilder {
             classpath_entries: Vec::new(),
             java_opts: Vec::new(),
@@ -963,17 +1272,34 @@ impl<'a> JvmBuilder<'a> {
             skip_setting_native_lib: false,
             base_path: None,
             maven_settings: MavenSettings::default(),
+            attach_options: AttachOptions::default(),
+            fail_on_ignored_options: false,
+            fail_on_missing_classpath_entries: false,
+            system_properties: Vec::new(),
+            agents: Vec::new(),
+            module_path_entries: Vec::new(),
+            add_modules: Vec::new(),
+            add_opens: Vec::new(),
+            add_exports: Vec::new(),
+            min_java_version: None,
+            jni_version: JniVersion::default(),
+            config_file: None,
+            env_config: false,
+            jvm_lib: None,
         }
     }
 
     /// Adds a classpath entry.
-    pub fn classpath_entry(&'a mut self, cp_entry: ClasspathEntry<'a>) -> &'a mut JvmBuilder {
+    ///
+    /// Besides jars and directories of classes, an entry may be a directory that contains jars,
+    /// or a glob pattern like `lib/*` or `lib/**/*.jar`. These are resolved to the jars they contain when `build` is called.
+    pub fn classpath_entry(&mut self, cp_entry: ClasspathEntry) -> &mut JvmBuilder {
         self.classpath_entries.push(cp_entry);
         self
     }
 
     /// Adds classpath entries.
-    pub fn classpath_entries(&'a mut self, cp_entries: Vec<ClasspathEntry<'a>>) -> &'a mut JvmBuilder {
+    pub fn classpath_entries(&mut self, cp_entries: Vec<ClasspathEntry>) -> &mut JvmBuilder {
         for cp_entry in cp_entries {
             self.classpath_entries.push(cp_entry);
         }
@@ -981,22 +1307,101 @@ impl<'a> JvmBuilder<'a> {
     }
 
     /// Adds a Java option.
-    pub fn java_opt(&'a mut self, opt: JavaOpt<'a>) -> &'a mut JvmBuilder {
+    pub fn java_opt(&mut self, opt: JavaOpt) -> &mut JvmBuilder {
         self.java_opts.push(opt);
         self
     }
 
     /// Adds Java options.
-    pub fn java_opts(&'a mut self, opts: Vec<JavaOpt<'a>>) -> &'a mut JvmBuilder {
+    pub fn java_opts(&mut self, opts: Vec<JavaOpt>) -> &mut JvmBuilder {
         for opt in opts {
             self.java_opts.push(opt);
         }
         self
     }
 
+    /// Sets a Java system property, like a `-Dkey=value` Java option does.
+    ///
+    /// The value may be of any type that can be converted to a string and is passed to the JVM as is.
+    /// Keys that are empty or contain `=`, as well as keys and values that contain NUL characters,
+    /// make `build` return an error.
+    pub fn system_property<V: ToString>(&mut self, key: &str, value: V) -> &mut JvmBuilder {
+        self.system_properties.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets Java system properties. See `system_property`.
+    pub fn system_properties<I, K, V>(&mut self, properties: I) -> &mut JvmBuilder
+        where I: IntoIterator<Item=(K, V)>, K: ToString, V: ToString {
+        for (key, value) in properties {
+            self.system_properties.push((key.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Adds a module path entry: a modular jar, or a directory of modules.
+    ///
+    /// Unless modules are added with `add_module`, all the modules of the module path are resolved.
+    /// The module settings require Java 9 or newer.
+    pub fn module_path_entry(&mut self, module_path_entry: &str) -> &mut JvmBuilder {
+        self.module_path_entries.push(module_path_entry.to_string());
+        self
+    }
+
+    /// Adds a module to resolve, like the `--add-modules` Java option does.
+    ///
+    /// Besides module names, `ALL-MODULE-PATH`, `ALL-SYSTEM` and `ALL-DEFAULT` are accepted.
+    pub fn add_module(&mut self, module: &str) -> &mut JvmBuilder {
+        self.add_modules.push(module.to_string());
+        self
+    }
+
+    /// Opens a package of a module for deep reflection by j4rs, like `--add-opens=<module>/<package>=ALL-UNNAMED` does.
+    ///
+    /// E.g. `add_opens("java.base", "java.lang")` is needed on Java 17 for reflective access to the private members of `java.lang` classes.
+    pub fn add_opens(&mut self, module: &str, package: &str) -> &mut JvmBuilder {
+        self.add_opens.push(format!("{}/{}", module, package));
+        self
+    }
+
+    /// Exports a package of a module to j4rs, like `--add-exports=<module>/<package>=ALL-UNNAMED` does.
+    pub fn add_exports(&mut self, module: &str, package: &str) -> &mut JvmBuilder {
+        self.add_exports.push(format!("{}/{}", module, package));
+        self
+    }
+
+    /// Adds a Java agent, like the `-javaagent` Java option does.
+    ///
+    /// A relative `jar_path` is resolved against the jassets directory. The `options` are passed to the agent.
+    /// The agents are loaded in the order that they are added.
+    pub fn java_agent(&mut self, jar_path: &str, options: Option<&str>) -> &mut JvmBuilder {
+        self.agents.push(Agent::Java { jar: AgentJar::Path(jar_path.to_string()), options: options.map(|o| o.to_string()) });
+        self
+    }
+
+    /// Adds a Java agent that is a Maven artifact.
+    ///
+    /// The agent jar is expected in the jassets directory, where `Jvm::deploy_artifact` deploys the artifacts.
+    /// If it is not deployed, it is copied there from the local Maven repository (`~/.m2/repository`), if it is found,
+    /// or else it is fetched from the repositories of the Maven settings, before the Java VM is created.
+    /// The `file`, `http` and `https` repositories are supported.
+    pub fn java_agent_artifact(&mut self, artifact: MavenArtifact, options: Option<&str>) -> &mut JvmBuilder {
+        self.agents.push(Agent::Java { jar: AgentJar::Artifact(artifact), options: options.map(|o| o.to_string()) });
+        self
+    }
+
+    /// Adds a native agent library, like the `-agentpath` Java option does.
+    ///
+    /// A relative `lib_path` is resolved against the base path, which contains the jassets and deps directories.
+    /// The `options` are passed to the agent.
+    pub fn native_agent(&mut self, lib_path: &str, options: Option<&str>) -> &mut JvmBuilder {
+        self.agents.push(Agent::Native { path: lib_path.to_string(), options: options.map(|o| o.to_string()) });
+        self
+    }
+
     /// By default, the created `Jvm`s include an implicit classpath entry that includes the j4rs jar.
     /// When `with_no_implicit_classpath()` is called, this classpath will not be added to the Jvm.
-    pub fn with_no_implicit_classpath(&'a mut self) -> &'a mut JvmBuilder {
+    pub fn with_no_implicit_classpath(&mut self) -> &mut JvmBuilder {
         self.no_implicit_classpath = true;
         self
     }
@@ -1006,7 +1411,7 @@ impl<'a> JvmBuilder<'a> {
     ///
     /// This is useful when in the Java world a native method is called and in the native code someone needs to create a j4rs Jvm.
     /// If that Jvm detaches its current thread when being dropped, there will be problems for the Java world code to continue executing.
-    pub fn detach_thread_on_drop(&'a mut self, detach_thread_on_drop: bool) -> &'a mut JvmBuilder {
+    pub fn detach_thread_on_drop(&mut self, detach_thread_on_drop: bool) -> &mut JvmBuilder {
         self.detach_thread_on_drop = detach_thread_on_drop;
         self
     }
@@ -1015,74 +1420,170 @@ impl<'a> JvmBuilder<'a> {
     /// library instead of the default one.
     ///
     /// This function defines the native library name to load.
-    pub fn with_native_lib_name(&'a mut self, lib_name: &str) -> &'a mut JvmBuilder {
+    pub fn with_native_lib_name(&mut self, lib_name: &str) -> &mut JvmBuilder {
         self.lib_name_opt = Some(lib_name.to_string());
         self
     }
 
     /// Instructs the builder not to instruct the Java world j4rs code not to load the native library.
     /// (most probably because it is already loaded)
-    pub fn skip_setting_native_lib(&'a mut self) -> &'a mut JvmBuilder {
+    pub fn skip_setting_native_lib(&mut self) -> &mut JvmBuilder {
         self.skip_setting_native_lib = true;
         self
     }
 
     /// Defines the location of the jassets and deps directory.
     /// The jassets contains the j4rs jar and the deps the j4rs dynamic library.
-    pub fn with_base_path(&'a mut self, base_path: &str) -> &'a mut JvmBuilder {
+    ///
+    /// The base path of a configuration file or of the `J4RS_BASE_PATH` environment variable replaces this one,
+    /// as described in `with_env_config`. If no base path is defined, the jassets directory is searched in the
+    /// `J4RS_JASSETS_PATH` environment variable, the directory of the executable and the cargo target directory,
+    /// which is up to two levels above the directory of the executable.
+    /// If it is not found, `build` returns an error that lists the searched locations.
+    pub fn with_base_path(&mut self, base_path: &str) -> &mut JvmBuilder {
         self.base_path = Some(base_path.to_string());
         self
     }
 
     /// Defines the maven settings to use for provisioning maven artifacts.
-    pub fn with_maven_settings(&'a mut self, maven_settings: MavenSettings) -> &'a mut JvmBuilder {
+    pub fn with_maven_settings(&mut self, maven_settings: MavenSettings) -> &mut JvmBuilder {
         self.maven_settings = maven_settings;
         self
     }
 
-    /// Creates a Jvm
-    pub fn build(&self) -> errors::Result<Jvm> {
-        let classpath = if self.no_implicit_classpath {
-            self.classpath_entries
-                .iter()
-                .fold(
-                    ".".to_string(),
-                    |all, elem| {
-                        format!("{}{}{}", all, utils::classpath_sep(), elem.to_string())
-                    })
-        } else {
-            // The default classpath contains all the jars in the jassets directory
-            let jassets_path = match &self.base_path {
-                Some(base_path_string) => {
-                    let mut pb = PathBuf::from(base_path_string);
-                    pb.push("jassets");
-                    let mut global_jassets_path_opt = cache::JASSETS_PATH.lock()?;
-                    *global_jassets_path_opt = Some(pb.clone());
-                    pb
-                }
-                None => utils::default_jassets_path()?,
-            };
-            let all_jars = get_dir_content(&jassets_path)?.files;
-            // This is the j4rs jar that should be included in the classpath
-            let j4rs_jar_to_use = format!("j4rs-{}-jar-with-dependencies.jar", j4rs_version());
-            // Filter out possible incorrect jars of j4rs
-            let filtered_jars: Vec<String> = all_jars.into_iter()
-                .filter(|jar| {
-                    !jar.contains("j4rs-") || jar.ends_with(&j4rs_jar_to_use)
-                })
-                .collect();
-            let cp_string = filtered_jars.join(utils::classpath_sep());
+    /// Defines how the current thread is attached, in case that the Java VM is already created by another thread.
+    pub fn with_attach_options(&mut self, attach_options: AttachOptions) -> &mut JvmBuilder {
+        self.attach_options = attach_options;
+        self
+    }
+
+    /// If a Java VM is already created, `build` attaches to it and the classpath entries, the Java options
+    /// and the native library name of this builder cannot be applied.
+    ///
+    /// By default, the options that are ignored this way are logged as warnings.
+    /// Calling this method makes `build` return an error instead.
+    pub fn fail_on_ignored_options(&mut self) -> &mut JvmBuilder {
+        self.fail_on_ignored_options = true;
+        self
+    }
 
-            let default_class_path = format!("-Djava.class.path={}", cp_string);
+    /// By default, the classpath entries that do not exist, or the patterns that match no jars, are logged as warnings
+    /// and left out of the classpath. The same applies to the module path entries.
+    /// Calling this method makes `build` return an error instead.
+    pub fn fail_on_missing_classpath_entries(&mut self) -> &mut JvmBuilder {
+        self.fail_on_missing_classpath_entries = true;
+        self
+    }
 
-            self.classpath_entries
-                .iter()
-                .fold(
-                    default_class_path,
-                    |all, elem| {
-                        format!("{}{}{}", all, utils::classpath_sep(), elem.to_string())
-                    })
+    /// Requires a Java runtime of at least the provided feature release, like 11 for Java 11.
+    ///
+    /// `build` returns an error if the JVM runs an older Java version.
+    pub fn with_min_java_version(&mut self, feature: u32) -> &mut JvmBuilder {
+        self.min_java_version = Some(feature);
+        self
+    }
+
+    /// Defines the JNI version that is requested when creating the JVM. The default is `JniVersion::V1_8`.
+    ///
+    /// Creating the JVM fails if the Java runtime does not support the requested version.
+    pub fn with_jni_version(&mut self, jni_version: JniVersion) -> &mut JvmBuilder {
+        self.jni_version = jni_version;
+        self
+    }
+
+    /// Defines the Java installation whose jvm dynamic library (e.g. `lib/server/libjvm.so`) is loaded in order to create the JVM.
+    ///
+    /// By default, the library is located using the `JAVA_HOME` environment variable, or the `java` executable of the `PATH`.
+    pub fn with_java_home(&mut self, java_home: &str) -> &mut JvmBuilder {
+        self.jvm_lib = Some(JvmLibLocation::JavaHome(PathBuf::from(java_home)));
+        self
+    }
+
+    /// Defines the jvm dynamic library (`libjvm.so`, `libjvm.dylib` or `jvm.dll`) that is loaded in order to create the JVM.
+    ///
+    /// The path may point to the library file, or to the directory that contains it.
+    pub fn with_jvm_lib(&mut self, jvm_lib_path: &str) -> &mut JvmBuilder {
+        self.jvm_lib = Some(JvmLibLocation::LibPath(PathBuf::from(jvm_lib_path)));
+        self
+    }
+
+    /// Merges the settings of a TOML or JSON configuration file into the settings of this builder.
+    ///
+    /// The file may define `classpath`, `java_opts`, `base_path` and `maven_repos` (as `id::uri` strings).
+    /// It is read when `build` is called. See `with_env_config` for the precedence of the settings.
+    pub fn with_config_file(&mut self, path: &str) -> &mut JvmBuilder {
+        self.config_file = Some(PathBuf::from(path));
+        self
+    }
+
+    /// Merges the settings of the environment variables into the settings of this builder, when `build` is called:
+    ///
+    /// * `J4RS_CLASSPATH`: classpath entries, separated by the classpath separator of the platform
+    /// * `J4RS_JAVA_OPTS`: Java options, separated by whitespace. Single or double quotes group text that contains whitespace,
+    ///   like `-Dapp.name="My App"`, and are removed
+    /// * `J4RS_BASE_PATH`: the location of the jassets and deps directories
+    /// * `J4RS_MAVEN_REPOS`: Maven repositories, as comma separated `id::uri` values
+    /// * `J4RS_CONFIG_FILE`: a TOML or JSON configuration file, like the one of `with_config_file`
+    ///
+    /// The settings are applied with increasing precedence: the builder calls, the file of `with_config_file`,
+    /// the file of `J4RS_CONFIG_FILE` and the environment variables.
+    /// The base path of the highest precedence replaces the others. The classpath entries and the Java options are appended,
+    /// so that the JVM applies the last one of any repeated option. The Maven repositories of higher precedence are searched first.
+    pub fn with_env_config(&mut self) -> &mut JvmBuilder {
+        self.env_config = true;
+        self
+    }
+
+    // The Java option that sets a system property
+    fn system_property_opt(key: &str, value: &str) -> errors::Result<String> {
+        if key.is_empty() || key.contains('=') || key.contains('\0') || value.contains('\0') {
+            Err(errors::J4RsError::GeneralError(format!("Invalid system property {:?} with value {:?}", key, value)))
+        } else {
+            Ok(format!("-D{}={}", key, value))
+        }
+    }
+
+    // The settings of the configuration files and the environment variables
+    fn external_config(&self) -> errors::Result<JvmConfig> {
+        let mut config = match &self.config_file {
+            Some(path) => JvmConfig::from_file(path)?,
+            None => JvmConfig::default(),
         };
+        if self.env_config {
+            config = config.merge(JvmConfig::from_env()?);
+        }
+        Ok(config)
+    }
+
+    /// Creates a Jvm
+    pub fn build(&self) -> errors::Result<Jvm> {
+        let external_config = self.external_config()?;
+        let classpath_entries: Vec<String> = self.classpath_entries.iter()
+            .map(|entry| entry.to_string())
+            .chain(external_config.classpath.iter().cloned())
+            .collect();
+        let base_path = external_config.base_path.clone().or_else(|| self.base_path.clone());
+        let jassets_path = Self::jassets_path(&base_path)?;
+        let maven_settings = self.maven_settings_with(&external_config);
+        let java_opts = self.java_opts_with(&external_config, &jassets_path, &maven_settings)?;
+        if !external_config.is_empty() {
+            info(&format!("Merged the external JVM configuration: {}", external_config));
+        }
+        info(&format!("Effective JVM configuration: {}", JvmConfig {
+            classpath: classpath_entries.clone(),
+            java_opts: java_opts.clone(),
+            base_path: base_path.clone(),
+            maven_repos: maven_settings.repos.iter().map(|repo| format!("{}::{}", repo.id, repo.uri)).collect(),
+        }));
+
+        if base_path.is_some() {
+            let mut global_jassets_path_opt = cache::JASSETS_PATH.lock()?;
+            *global_jassets_path_opt = Some(jassets_path.clone());
+        }
+        let resolved_classpath_entries = self.resolve_classpath_entries(&classpath_entries)?;
+        let mut full_classpath = self.implicit_classpath(&jassets_path)?;
+        full_classpath.extend(resolved_classpath_entries.iter().cloned());
+        let classpath = format!("-Djava.class.path={}", utils::dedup_classpath(full_classpath).join(utils::classpath_sep()));
         info(&format!("Setting classpath to {}", classpath));
 
         // Populate the JVM Options
@@ -1093,7 +1594,7 @@ impl<'a> JvmBuilder<'a> {
             info(&format!("Setting library path to {}", default_library_path));
             vec![classpath, default_library_path]
         };
-        self.java_opts.clone().into_iter().for_each(|opt| jvm_options.push(opt.to_string()));
+        jvm_options.extend(java_opts.iter().cloned());
 
         // Pass to the Java world the name of the j4rs library.
         let lib_name_opt = if self.lib_name_opt.is_none() && !self.skip_setting_native_lib {
@@ -1131,17 +1632,168 @@ impl<'a> JvmBuilder<'a> {
             None
         };
 
-        provisioning::set_maven_settings(&self.maven_settings);
+        provisioning::set_maven_settings(&maven_settings);
 
-        Jvm::new(&jvm_options, lib_name_opt)
-            .and_then(|mut jvm| {
+        Jvm::create_jvm(&jvm_options, lib_name_opt.clone(), &self.attach_options, self.jvm_lib.as_ref(), self.jni_version)
+            .and_then(|(mut jvm, created)| {
                 if !self.detach_thread_on_drop {
                     jvm.detach_thread_on_drop(false);
                 }
+                if let Some(min_java_version) = self.min_java_version {
+                    let java_version = jvm.java_version()?;
+                    if java_version.feature() < min_java_version {
+                        return Err(errors::J4RsError::GeneralError(format!(
+                            "Java {} or newer is required, but the JVM runs Java {}", min_java_version, java_version)));
+                    }
+                }
+                if !created {
+                    self.check_ignored_options(&jvm, &resolved_classpath_entries, &java_opts, lib_name_opt.as_deref())?;
+                }
                 Ok(jvm)
             })
     }
 
+    /// Returns the classpath that `build` passes to a new Java VM.
+    ///
+    /// The directories and the glob patterns of the classpath entries are resolved to the jars they contain
+    /// and the duplicate jars are removed.
+    pub fn resolved_classpath(&self) -> errors::Result<Vec<String>> {
+        let external_config = self.external_config()?;
+        let classpath_entries: Vec<String> = self.classpath_entries.iter()
+            .map(|entry| entry.to_string())
+            .chain(external_config.classpath.iter().cloned())
+            .collect();
+        let base_path = external_config.base_path.clone().or_else(|| self.base_path.clone());
+        let mut full_classpath = self.implicit_classpath(&Self::jassets_path(&base_path)?)?;
+        full_classpath.extend(self.resolve_classpath_entries(&classpath_entries)?);
+        Ok(utils::dedup_classpath(full_classpath))
+    }
+
+    /// Returns the Java options that `build` passes to a new Java VM, apart from the classpath and the library path.
+    #[cfg(test)]
+    pub(crate) fn effective_java_opts(&self) -> errors::Result<Vec<String>> {
+        let external_config = self.external_config()?;
+        let base_path = external_config.base_path.clone().or_else(|| self.base_path.clone());
+        self.java_opts_with(&external_config, &Self::jassets_path(&base_path)?, &self.maven_settings_with(&external_config))
+    }
+
+    // The Maven settings of the builder. The repositories of the external configuration are searched first.
+    fn maven_settings_with(&self, external_config: &JvmConfig) -> MavenSettings {
+        let mut maven_settings = self.maven_settings.clone();
+        maven_settings.repos.splice(0..0, external_config.maven_repos.iter().map(|repo| MavenArtifactRepo::from(repo.as_str())));
+        maven_settings
+    }
+
+    // The Java options of the builder, followed by the ones of the external configuration
+    fn java_opts_with(&self, external_config: &JvmConfig, jassets_path: &Path, maven_settings: &MavenSettings) -> errors::Result<Vec<String>> {
+        let mut java_opts: Vec<String> = self.java_opts.iter()
+            .map(|opt| opt.to_string())
+            .collect();
+        for (key, value) in &self.system_properties {
+            java_opts.push(Self::system_property_opt(key, value)?);
+        }
+        java_opts.extend(external_config.java_opts.iter().cloned());
+        // The agents are loaded before anything else, followed by the module settings
+        let agent_opts = self.agents.iter()
+            .map(|agent| agent.java_opt(jassets_path, &maven_settings.repos))
+            .collect::<errors::Result<Vec<String>>>()?;
+        java_opts.splice(0..0, agent_opts.into_iter().chain(self.module_opts()?));
+        Ok(java_opts)
+    }
+
+    fn jassets_path(base_path: &Option<String>) -> errors::Result<PathBuf> {
+        match base_path {
+            Some(base_path_string) => {
+                let mut pb = PathBuf::from(base_path_string);
+                pb.push("jassets");
+                Ok(pb)
+            }
+            None => utils::default_jassets_path(),
+        }
+    }
+
+    // The classpath entries that are added by j4rs
+    fn implicit_classpath(&self, jassets_path: &Path) -> errors::Result<Vec<String>> {
+        if self.no_implicit_classpath {
+            Ok(vec![".".to_string()])
+        } else {
+            // The default classpath contains all the jars in the jassets directory
+            let all_jars = get_dir_content(jassets_path)?.files;
+            // This is the j4rs jar that should be included in the classpath
+            let j4rs_jar_to_use = format!("j4rs-{}-jar-with-dependencies.jar", j4rs_version());
+            // Filter out possible incorrect jars of j4rs
+            Ok(all_jars.into_iter()
+                .filter(|jar| {
+                    !jar.contains("j4rs-") || jar.ends_with(&j4rs_jar_to_use)
+                })
+                .collect())
+        }
+    }
+
+    // Resolves the directories and the patterns of the classpath entries and handles the missing ones
+    fn resolve_classpath_entries(&self, classpath_entries: &[String]) -> errors::Result<Vec<String>> {
+        let mut resolved = Vec::new();
+        let mut missing = Vec::new();
+        for entry in classpath_entries {
+            let paths = utils::resolve_classpath_entry(entry)?;
+            if paths.is_empty() {
+                missing.push(entry.clone());
+            }
+            resolved.extend(paths);
+        }
+        self.check_missing_entries("classpath", &missing)?;
+        Ok(resolved)
+    }
+
+    // The Java options of the module settings
+    fn module_opts(&self) -> errors::Result<Vec<String>> {
+        let mut opts = Vec::new();
+        if !self.module_path_entries.is_empty() {
+            let (existing, missing): (Vec<String>, Vec<String>) = self.module_path_entries.iter()
+                .cloned()
+                .partition(|entry| Path::new(entry).exists());
+            self.check_missing_entries("module path", &missing)?;
+            if !existing.is_empty() {
+                opts.push(format!("--module-path={}", existing.join(utils::classpath_sep())));
+            }
+        }
+        if !self.add_modules.is_empty() {
+            opts.push(format!("--add-modules={}", self.add_modules.join(",")));
+        } else if !self.module_path_entries.is_empty() {
+            // The modules of the module path are not resolved, unless they are added
+            opts.push("--add-modules=ALL-MODULE-PATH".to_string());
+        }
+        // j4rs accesses the Java classes from the unnamed module
+        opts.extend(self.add_opens.iter().map(|opens| format!("--add-opens={}=ALL-UNNAMED", opens)));
+        opts.extend(self.add_exports.iter().map(|exports| format!("--add-exports={}=ALL-UNNAMED", exports)));
+        Ok(opts)
+    }
+
+    // Handles the classpath or module path entries that do not exist
+    fn check_missing_entries(&self, path_name: &str, missing: &[String]) -> errors::Result<()> {
+        if missing.is_empty() {
+            Ok(())
+        } else if self.fail_on_missing_classpath_entries {
+            Err(errors::J4RsError::GeneralError(format!("The {} entries [{}] do not exist", path_name, missing.join(", "))))
+        } else {
+            warn(&format!("Leaving out of the {} the entries that do not exist: [{}]", path_name, missing.join(", ")));
+            Ok(())
+        }
+    }
+
+    // Checks whether the options of this builder apply to the Java VM that already existed
+    fn check_ignored_options(&self, jvm: &Jvm, classpath_entries: &[String], java_opts: &[String], lib_name: Option<&str>) -> errors::Result<()> {
+        let ignored = IgnoredOptions::find(jvm, classpath_entries, java_opts, lib_name)?;
+        if ignored.is_empty() {
+            Ok(())
+        } else if self.fail_on_ignored_options {
+            Err(errors::J4RsError::GeneralError(format!("A JVM is already created and some options cannot be applied to it: {}", ignored)))
+        } else {
+            warn(&format!("A JVM is already created. Ignoring the options: {}", ignored));
+            Ok(())
+        }
+    }
+
     /// Creates a Jvm, similar with an already created j4rs Jvm.
     ///
     /// _Note: The already created Jvm is a j4rs Jvm, not a Java VM._
@@ -1150,6 +1802,107 @@ impl<'a> JvmBuilder<'a> {
     }
 }
 
+// An agent that is loaded when the Java VM is created
+#[derive(Debug, Clone, Serialize)]
+enum Agent {
+    Java { jar: AgentJar, options: Option<String> },
+    Native { path: String, options: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+enum AgentJar {
+    Path(String),
+    Artifact(MavenArtifact),
+}
+
+impl Agent {
+    // The Java option that loads the agent. Missing Maven artifacts are fetched from the Maven repositories.
+    fn java_opt(&self, jassets_path: &Path, maven_repos: &[MavenArtifactRepo]) -> errors::Result<String> {
+        // The base path contains the jassets directory
+        let base_path = jassets_path.parent().unwrap_or(jassets_path);
+        let (opt_name, path, options) = match self {
+            Agent::Java { jar: AgentJar::Path(path), options } => ("-javaagent", jassets_path.join(path), options),
+            Agent::Java { jar: AgentJar::Artifact(artifact), options } => {
+                let local_repository = provisioning::local_maven_repository();
+                ("-javaagent", provisioning::provision_artifact(artifact, local_repository.as_deref(), maven_repos, jassets_path)?, options)
+            }
+            Agent::Native { path, options } => ("-agentpath", base_path.join(path), options),
+        };
+        if !path.is_file() {
+            return Err(errors::J4RsError::GeneralError(format!("The agent {} does not exist", path.display())));
+        }
+        let path = path.to_str()
+            .ok_or_else(|| errors::J4RsError::GeneralError(format!("The agent path {} is not valid unicode", path.display())))?;
+        // The Java VM takes whatever follows the first `=` as the agent options
+        if path.contains('=') {
+            return Err(errors::J4RsError::GeneralError(format!("The agent path {} cannot contain '='", path)));
+        }
+        Ok(match options {
+            Some(options) => format!("{}:{}={}", opt_name, path, options),
+            None => format!("{}:{}", opt_name, path),
+        })
+    }
+}
+
+impl Default for JvmBuilder {
+    fn default() -> Self {
+        JvmBuilder::new()
+    }
+}
+
+// The options of a JvmBuilder that are not applied to an already created Java VM.
+struct IgnoredOptions {
+    classpath_entries: Vec<String>,
+    java_opts: Vec<String>,
+    lib_name: Option<String>,
+}
+
+impl IgnoredOptions {
+    fn find(jvm: &Jvm, classpath_entries: &[String], java_opts: &[String], lib_name: Option<&str>) -> errors::Result<IgnoredOptions> {
+        let live_entries = jvm.classpath()?;
+        let classpath_entries = classpath_entries.iter()
+            .filter(|entry| !live_entries.iter().any(|live_entry| utils::same_path(live_entry, entry)))
+            .cloned()
+            .collect();
+
+        let runtime = jvm.invoke_static("java.lang.management.ManagementFactory", "getRuntimeMXBean", &[])?;
+        // The implementing class is not accessible
+        let runtime = jvm.cast(&runtime, "java.lang.management.RuntimeMXBean")?;
+        let input_arguments: Vec<String> = jvm.to_rust(jvm.invoke(&runtime, "getInputArguments", &[])?)?;
+        let java_opts = java_opts.iter()
+            .filter(|opt| !input_arguments.contains(opt))
+            .cloned()
+            .collect();
+
+        let lib_name = match (lib_name, cache::NATIVE_LIB_NAME.lock()?.as_deref()) {
+            (Some(requested), Some(loaded)) if requested != loaded => Some(requested.to_string()),
+            _ => None,
+        };
+
+        Ok(IgnoredOptions { classpath_entries, java_opts, lib_name })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.classpath_entries.is_empty() && self.java_opts.is_empty() && self.lib_name.is_none()
+    }
+}
+
+impl fmt::Display for IgnoredOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut descriptions = Vec::new();
+        if !self.classpath_entries.is_empty() {
+            descriptions.push(format!("classpath entries [{}] are not in the java.class.path", self.classpath_entries.join(", ")));
+        }
+        if !self.java_opts.is_empty() {
+            descriptions.push(format!("Java options [{}] are not in the input arguments", self.java_opts.join(", ")));
+        }
+        if let Some(lib_name) = &self.lib_name {
+            descriptions.push(format!("native library {} is not the loaded one", lib_name));
+        }
+        write!(f, "{}", descriptions.join("; "))
+    }
+}
+
 /// Struct that carries an argument that is used for method invocations in Java.
 #[derive(Serialize)]
 pub enum InvocationArg {
@@ -1191,6 +1944,7 @@ impl InvocationArg {
     pub fn new_2<T>(arg: &T, class_name: &str, jni_env: *mut JNIEnv) -> errors::Result<InvocationArg>
         where T: Serialize + Any
     {
+        cache::ensure_jvm_not_destroyed()?;
         let arg_any = arg as &dyn Any;
         if let Some(a) = arg_any.downcast_ref::<String>() {
             Ok(InvocationArg::RustBasic {
@@ -1258,6 +2012,7 @@ impl InvocationArg {
 
     /// Creates a `jobject` from this InvocationArg.
     pub fn as_java_ptr(&self, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
+        cache::ensure_jvm_not_destroyed()?;
         match self {
             _s @ &InvocationArg::Java { .. } => jni_utils::invocation_arg_jobject_from_java(&self, jni_env),
             _s @ &InvocationArg::Rust { .. } => jni_utils::invocation_arg_jobject_from_rust_serialized(&self, jni_env),
@@ -1265,6 +2020,30 @@ impl InvocationArg {
         }
     }
 
+    /// Creates an InvocationArg that contains a Java `null`, which is typed as defined by the passed `Null`.
+    ///
+    /// The type is needed in order for Java to match the method or the constructor to invoke.
+    pub fn create_null(null: Null) -> InvocationArg {
+        let class_name = match null {
+            Null::String => "java.lang.String",
+            Null::Boolean => "java.lang.Boolean",
+            Null::Byte => "java.lang.Byte",
+            Null::Character => "java.lang.Character",
+            Null::Short => "java.lang.Short",
+            Null::Integer => "java.lang.Integer",
+            Null::Long => "java.lang.Long",
+            Null::Float => "java.lang.Float",
+            Null::Double => "java.lang.Double",
+            Null::List => "java.util.List",
+            Null::Of(class_name) => class_name,
+        };
+        InvocationArg::RustBasic {
+            instance: Instance::new(ptr::null_mut(), class_name),
+            class_name: class_name.to_string(),
+            serialized: false,
+        }
+    }
+
     /// Consumes this invocation arg and returns its Instance
     pub fn instance(self) -> errors::Result<Instance> {
         match self {
@@ -1528,6 +2307,23 @@ impl<'a> TryFrom<&'a f64> for InvocationArg {
     }
 }
 
+/// Defines the type of a Java `null` that is passed as an `InvocationArg`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Null<'a> {
+    String,
+    Boolean,
+    Byte,
+    Character,
+    Short,
+    Integer,
+    Long,
+    Float,
+    Double,
+    List,
+    /// A `null` of the class with the provided fully qualified name.
+    Of(&'a str),
+}
+
 /// A receiver for Java Instances.
 ///
 /// It keeps a channel Receiver to get callback Instances from the Java world
@@ -1541,7 +2337,9 @@ pub struct InstanceReceiver {
 }
 
 impl InstanceReceiver {
-    fn new(rx: Receiver<Instance>, tx_address: i64) -> InstanceReceiver {
+    #[track_caller]
+    fn new(rx: Receiver<Instance>, tx_address: i64, class_name: &str) -> InstanceReceiver {
+        diagnostics::track_callback_channel(tx_address, class_name);
         InstanceReceiver {
             rx: Box::new(rx),
             tx_address,
@@ -1556,6 +2354,7 @@ impl InstanceReceiver {
 impl Drop for InstanceReceiver {
     fn drop(&mut self) {
         debug("Dropping an InstanceReceiver");
+        diagnostics::untrack_callback_channel(self.tx_address);
         let p = self.tx_address as *mut Sender<Instance>;
         unsafe {
             let tx = Box::from_raw(p);
@@ -1564,6 +2363,117 @@ impl Drop for InstanceReceiver {
     }
 }
 
+/// A lazy iterator over the elements of a Java `Iterable`, `Iterator`, `Enumeration`, `Stream` or array.
+///
+/// It is created by `Jvm::iterate`.
+pub struct InstanceIterator<'a> {
+    batch_iterator: Instance,
+    jvm: &'a Jvm,
+    batch_size: usize,
+    buffer: VecDeque<Instance>,
+    exhausted: bool,
+}
+
+impl<'a> InstanceIterator<'a> {
+    fn new(batch_iterator: Instance, jvm: &'a Jvm) -> InstanceIterator<'a> {
+        InstanceIterator {
+            batch_iterator,
+            jvm,
+            batch_size: 1,
+            buffer: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Defines how many elements are retrieved from the Java world at once. The default is 1.
+    ///
+    /// At most `batch_size` elements are kept in memory at any time. Values above `i32::MAX` are treated as `i32::MAX`.
+    pub fn batch_size(mut self, batch_size: usize) -> InstanceIterator<'a> {
+        self.batch_size = batch_size.clamp(1, i32::MAX as usize);
+        self
+    }
+
+    /// Transforms this iterator to an iterator that maps each element to a Rust value (like `Jvm::to_rust` does).
+    ///
+    /// Each batch of elements is transformed with a single call to the Java world.
+    pub fn into_rust<T>(self) -> RustValuesIterator<'a, T> where T: DeserializeOwned {
+        RustValuesIterator {
+            instances: self,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    // Retrieves the next batch as a Java List, along with its size. Returns None if the iteration is over.
+    fn next_batch(&mut self) -> errors::Result<Option<(Instance, usize)>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        let batch_size = InvocationArg::try_from(i32::try_from(self.batch_size).unwrap_or(i32::MAX))?.into_primitive()?;
+        let batch = self.jvm.invoke(&self.batch_iterator, "nextBatch", &[batch_size])?;
+        let size: usize = self.jvm.to_rust(self.jvm.invoke(&batch, "size", &[])?)?;
+        // A batch that is not full means that there are no more elements
+        if size < self.batch_size {
+            self.exhausted = true;
+        }
+        Ok(if size == 0 { None } else { Some((batch, size)) })
+    }
+
+    fn fill_buffer(&mut self) -> errors::Result<()> {
+        if let Some((batch, size)) = self.next_batch()? {
+            for index in 0..size {
+                let index = InvocationArg::try_from(index as i32)?.into_primitive()?;
+                self.buffer.push_back(self.jvm.invoke(&batch, "get", &[index])?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for InstanceIterator<'a> {
+    type Item = errors::Result<Instance>;
+
+    fn next(&mut self) -> Option<errors::Result<Instance>> {
+        if self.buffer.is_empty() {
+            if let Err(error) = self.fill_buffer() {
+                // Do not continue the iteration after an error
+                self.exhausted = true;
+                return Some(Err(error));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+/// A lazy iterator that maps the elements of a Java `Iterable`, `Iterator`, `Enumeration`, `Stream` or array to Rust values.
+///
+/// It is created by `InstanceIterator::into_rust`.
+pub struct RustValuesIterator<'a, T> {
+    instances: InstanceIterator<'a>,
+    buffer: VecDeque<T>,
+}
+
+impl<'a, T> Iterator for RustValuesIterator<'a, T> where T: DeserializeOwned {
+    type Item = errors::Result<T>;
+
+    fn next(&mut self) -> Option<errors::Result<T>> {
+        if self.buffer.is_empty() {
+            let values = self.instances.next_batch()
+                .and_then(|batch_opt| match batch_opt {
+                    Some((batch, _)) => self.instances.jvm.to_rust::<Vec<T>>(batch),
+                    None => Ok(Vec::new()),
+                });
+            match values {
+                Ok(values) => self.buffer.extend(values),
+                Err(error) => {
+                    self.instances.exhausted = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
 /// A Java instance
 #[derive(Serialize)]
 pub struct Instance {
@@ -1577,7 +2487,9 @@ pub struct Instance {
 }
 
 impl Instance {
+    #[track_caller]
     pub(crate) fn new(obj: jobject, classname: &str) -> Instance {
+        diagnostics::track_instance(obj, classname);
         Instance {
             jinstance: obj,
             class_name: classname.to_string(),
@@ -1589,43 +2501,169 @@ impl Instance {
         self.class_name.as_ref()
     }
 
+    /// Returns true if the Java object that is contained in this Instance is `null`.
+    pub fn is_null(&self) -> errors::Result<bool> {
+        cache::ensure_jvm_not_destroyed()?;
+        let jni_env = cache::get_thread_local_env()?;
+        unsafe {
+            let is_null = (opt_to_res(cache::get_jni_call_boolean_method())?)(
+                jni_env,
+                self.jinstance,
+                cache::get_is_null_method()?,
+            );
+            Jvm::do_return(jni_env, is_null == JNI_TRUE)
+        }
+    }
+
+    // Returns a global reference to the Java object that this Instance wraps.
+    pub(crate) fn wrapped_object_global_ref(&self, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
+        unsafe {
+            let object = (opt_to_res(cache::get_jni_call_object_method())?)(
+                jni_env,
+                self.jinstance,
+                cache::get_get_object_method()?,
+            );
+            let object = Jvm::do_return(jni_env, object)?;
+            jni_utils::create_global_ref_from_local_ref(object, jni_env)
+        }
+    }
+
     /// Consumes the Instance and returns its jobject
     pub fn java_object(self) -> jobject {
         self.jinstance
     }
 
+    #[track_caller]
     pub fn from(obj: jobject) -> errors::Result<Instance> {
         let _jvm = cache::get_thread_local_env().map_err(|_| {
             Jvm::attach_thread()
         });
 
         let global = jni_utils::create_global_ref_from_local_ref(obj, cache::get_thread_local_env()?)?;
-        Ok(Instance {
-            jinstance: global,
-            class_name: cache::UNKNOWN_FOR_RUST.to_string(),
-        })
+        Ok(Instance::new(global, cache::UNKNOWN_FOR_RUST))
     }
 
-    /// Creates a weak reference of this Instance.
-    fn _weak_ref(&self) -> errors::Result<Instance> {
-        Ok(Instance {
-            class_name: self.class_name.clone(),
-            jinstance: jni_utils::_create_weak_global_ref_from_global_ref(self.jinstance.clone(), cache::get_thread_local_env()?)?,
-        })
+    /// Creates a weak reference to the Java object of this Instance.
+    ///
+    /// The weak reference does not prevent the Java object from being garbage collected.
+    #[track_caller]
+    pub fn downgrade(&self) -> errors::Result<WeakInstance> {
+        cache::ensure_jvm_not_destroyed()?;
+        let jni_env = cache::get_thread_local_env()?;
+        unsafe {
+            let object_class = (opt_to_res(cache::get_jni_call_object_method())?)(
+                jni_env,
+                self.jinstance,
+                cache::get_get_object_class_method()?,
+            );
+            let object_class = jni_utils::create_global_ref_from_local_ref(Jvm::do_return(jni_env, object_class)?, jni_env)?;
+            let object = match self.wrapped_object_global_ref(jni_env) {
+                Ok(object) => object,
+                Err(error) => {
+                    jni_utils::delete_java_ref(jni_env, object_class);
+                    return Err(error);
+                }
+            };
+            let weak_object = jni_utils::create_weak_global_ref_from_global_ref(object, jni_env);
+            jni_utils::delete_java_ref(jni_env, object);
+            match weak_object {
+                Ok(weak_object) => Ok(WeakInstance {
+                    class_name: self.class_name.clone(),
+                    weak_object,
+                    object_class,
+                }),
+                Err(error) => {
+                    jni_utils::delete_java_ref(jni_env, object_class);
+                    Err(error)
+                }
+            }
+        }
     }
 }
 
 impl Drop for Instance {
     fn drop(&mut self) {
         debug(&format!("Dropping an instance of {}", self.class_name));
-        if let Some(j_env) = cache::get_thread_local_env_opt() {
-            jni_utils::delete_java_ref(j_env, self.jinstance);
+        if self.jinstance.is_null() {
+            return;
+        }
+        diagnostics::untrack_instance(self.jinstance);
+        match cache::get_thread_local_env_opt() {
+            Some(j_env) => {
+                jni_utils::delete_java_ref(j_env, self.jinstance);
+                jni_utils::release_deferred_java_refs(j_env);
+            }
+            // The current thread cannot release the reference. Let an attached thread do it.
+            None => jni_utils::defer_java_ref_release(self.jinstance),
         }
     }
 }
 
 unsafe impl Send for Instance {}
 
+/// A weak reference to the Java object of an `Instance`.
+///
+/// It is created with `Instance::downgrade` and does not prevent the Java object from being garbage collected.
+#[derive(Debug)]
+pub struct WeakInstance {
+    /// The name of the class of the Instance that created this WeakInstance
+    class_name: String,
+    /// A weak global reference to the Java object
+    weak_object: jobject,
+    /// A global reference to the class that the Java object is treated as
+    object_class: jobject,
+}
+
+impl WeakInstance {
+    /// Creates an `Instance` of the Java object, or returns `None` if the object is garbage collected.
+    #[track_caller]
+    pub fn upgrade(&self) -> errors::Result<Option<Instance>> {
+        cache::ensure_jvm_not_destroyed()?;
+        let jni_env = cache::get_thread_local_env()?;
+        unsafe {
+            // A strong reference keeps the object alive until it is wrapped. It is null if the object is collected.
+            let object = (opt_to_res(cache::get_jni_new_global_ref())?)(jni_env, self.weak_object);
+            if object.is_null() {
+                return Ok(None);
+            }
+            let native_invocation_instance = (opt_to_res(cache::get_jni_call_static_object_method())?)(
+                jni_env,
+                cache::get_class_to_invoke_clone_and_cast()?,
+                cache::get_wrap_static_method()?,
+                object,
+                self.object_class,
+            );
+            jni_utils::delete_java_ref(jni_env, object);
+            let mut instance = Instance::from(Jvm::do_return(jni_env, native_invocation_instance)?)?;
+            instance.class_name = self.class_name.clone();
+            Ok(Some(instance))
+        }
+    }
+
+    pub fn class_name(&self) -> &str {
+        self.class_name.as_ref()
+    }
+}
+
+impl Drop for WeakInstance {
+    fn drop(&mut self) {
+        debug(&format!("Dropping a weak instance of {}", self.class_name));
+        match cache::get_thread_local_env_opt() {
+            Some(j_env) => {
+                jni_utils::delete_java_weak_ref(j_env, self.weak_object);
+                jni_utils::delete_java_ref(j_env, self.object_class);
+            }
+            // The current thread cannot release the references. Let an attached thread do it.
+            None => {
+                jni_utils::defer_java_weak_ref_release(self.weak_object);
+                jni_utils::defer_java_ref_release(self.object_class);
+            }
+        }
+    }
+}
+
+unsafe impl Send for WeakInstance {}
+
 /// Allows chained Jvm calls to created Instances
 pub struct ChainableInstance<'a> {
     instance: Instance,
@@ -1642,29 +2680,38 @@ impl<'a> ChainableInstance<'a> {
     }
 
     /// Invokes the method `method_name` of a this `Instance`, passing an array of `InvocationArg`s. It returns an `Instance` as the result of the invocation.
+    #[track_caller]
     pub fn invoke(&self, method_name: &str, inv_args: &[InvocationArg]) -> errors::Result<ChainableInstance> {
         let instance = self.jvm.invoke(&self.instance, method_name, inv_args)?;
         Ok(ChainableInstance::new(instance, self.jvm))
     }
 
     /// Creates a clone of the Instance
+    #[track_caller]
     pub fn clone_instance(&self) -> errors::Result<ChainableInstance> {
         let instance = self.jvm.clone_instance(&self.instance)?;
         Ok(ChainableInstance::new(instance, self.jvm))
     }
 
     /// Invokes the static method `method_name` of the class `class_name`, passing an array of `InvocationArg`s. It returns an `Instance` as the result of the invocation.
+    #[track_caller]
     pub fn cast(&self, to_class: &str) -> errors::Result<ChainableInstance> {
         let instance = self.jvm.cast(&self.instance, to_class)?;
         Ok(ChainableInstance::new(instance, self.jvm))
     }
 
     /// Retrieves the field `field_name` of the `Instance`.
+    #[track_caller]
     pub fn field(&self, field_name: &str) -> errors::Result<ChainableInstance> {
         let instance = self.jvm.field(&self.instance, field_name)?;
         Ok(ChainableInstance::new(instance, self.jvm))
     }
 
+    /// Returns true if the Java object that is contained in the `Instance` is `null`.
+    pub fn is_null(&self) -> errors::Result<bool> {
+        self.instance.is_null()
+    }
+
     /// Returns the Rust representation of the provided instance
     pub fn to_rust<T>(self) -> errors::Result<T> where T: DeserializeOwned {
         self.jvm.to_rust(self.instance)
@@ -1672,34 +2719,34 @@ impl<'a> ChainableInstance<'a> {
 }
 
 /// A classpath entry.
-#[derive(Debug, Clone)]
-pub struct ClasspathEntry<'a> (&'a str);
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClasspathEntry(String);
 
-impl<'a> ClasspathEntry<'a> {
+impl ClasspathEntry {
     pub fn new(classpath_entry: &str) -> ClasspathEntry {
-        ClasspathEntry(classpath_entry)
+        ClasspathEntry(classpath_entry.to_string())
     }
 }
 
-impl<'a> ToString for ClasspathEntry<'a> {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for ClasspathEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
 /// A Java Option.
-#[derive(Debug, Clone)]
-pub struct JavaOpt<'a> (&'a str);
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JavaOpt(String);
 
-impl<'a> JavaOpt<'a> {
+impl JavaOpt {
     pub fn new(java_opt: &str) -> JavaOpt {
-        JavaOpt(java_opt)
+        JavaOpt(java_opt.to_string())
     }
 }
 
-impl<'a> ToString for JavaOpt<'a> {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for JavaOpt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
@@ -1718,6 +2765,277 @@ mod api_unit_tests {
         assert!(one_more_res.is_ok());
     }
 
+    #[test]
+    fn ignored_builder_options() {
+        let jvm = JvmBuilder::new().build().unwrap();
+        let live_entry = jvm.classpath().unwrap().remove(0);
+        let live_opt = utils::java_library_path().unwrap();
+        let dir = std::env::temp_dir().join("j4rs_ignored_builder_options");
+        fs::create_dir_all(&dir).unwrap();
+        let not_in_classpath = dir.join("not_in_classpath.jar");
+        fs::write(&not_in_classpath, "").unwrap();
+        let not_in_classpath = not_in_classpath.to_str().unwrap();
+
+        // Options that are already applied
+        let res = JvmBuilder::new()
+            .classpath_entry(ClasspathEntry::new(&live_entry))
+            .java_opt(JavaOpt::new(&live_opt))
+            .fail_on_ignored_options()
+            .build();
+        assert!(res.is_ok());
+
+        // Options that cannot be applied
+        let res = JvmBuilder::new()
+            .classpath_entry(ClasspathEntry::new(not_in_classpath))
+            .java_opt(JavaOpt::new("-Dj4rs.never.set=true"))
+            .fail_on_ignored_options()
+            .build();
+        let error = format!("{}", res.err().unwrap());
+        assert!(error.contains(not_in_classpath) && error.contains("-Dj4rs.never.set=true"), "{}", error);
+
+        // By default, the ignored options are only logged
+        let res = JvmBuilder::new()
+            .classpath_entry(ClasspathEntry::new(not_in_classpath))
+            .build();
+        assert!(res.is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn system_properties() {
+        let jvm = JvmBuilder::new().build().unwrap();
+        let key = "j4rs.test.system_properties";
+
+        assert_eq!(jvm.system_property(key).unwrap(), None);
+        assert_eq!(jvm.set_system_property(key, "a value with spaces, = and ünicode").unwrap(), None);
+        assert_eq!(jvm.system_property(key).unwrap().as_deref(), Some("a value with spaces, = and ünicode"));
+        assert!(jvm.system_property_names().unwrap().contains(&key.to_string()));
+        let properties = jvm.system_properties().unwrap();
+        assert_eq!(properties.get(key).map(|value| value.as_str()), Some("a value with spaces, = and ünicode"));
+        assert_eq!(properties.get("java.class.path").cloned(), jvm.system_property("java.class.path").unwrap());
+        assert_eq!(jvm.set_system_property(key, "another").unwrap().as_deref(), Some("a value with spaces, = and ünicode"));
+        assert_eq!(jvm.clear_system_property(key).unwrap().as_deref(), Some("another"));
+        assert_eq!(jvm.system_property(key).unwrap(), None);
+
+        assert_eq!(JvmBuilder::system_property_opt("a.key", "a=value").unwrap(), "-Da.key=a=value");
+        assert_eq!(JvmBuilder::system_property_opt("a.key", "").unwrap(), "-Da.key=");
+        assert!(JvmBuilder::system_property_opt("", "value").is_err());
+        assert!(JvmBuilder::system_property_opt("a=key", "value").is_err());
+        assert!(JvmBuilder::system_property_opt("a.key", "val\0ue").is_err());
+
+        let res = JvmBuilder::new()
+            .system_property("a=key", 1)
+            .build();
+        assert!(format!("{}", res.err().unwrap()).contains("Invalid system property"));
+        // The system properties are Java options of the builder
+        let java_opts = JvmBuilder::new()
+            .java_opt(JavaOpt::new("-Xss2m"))
+            .system_property("j4rs.test.port", 8080)
+            .system_properties(vec![("j4rs.test.debug", true)])
+            .effective_java_opts()
+            .unwrap();
+        assert_eq!(java_opts, vec!["-Xss2m", "-Dj4rs.test.port=8080", "-Dj4rs.test.debug=true"]);
+    }
+
+    #[test]
+    fn java_version_requirements() {
+        let jvm = JvmBuilder::new().build().unwrap();
+        let java_version = jvm.java_version().unwrap();
+        assert!(java_version.feature() >= 8);
+        assert!(!java_version.vendor().is_empty());
+
+        assert!(JvmBuilder::new().with_min_java_version(8).build().is_ok());
+        let res = JvmBuilder::new().with_min_java_version(java_version.feature() + 1).build();
+        let error = format!("{}", res.err().unwrap());
+        assert!(error.contains(&format!("Java {} or newer is required", java_version.feature() + 1)), "{}", error);
+        assert!(error.contains(&java_version.to_string()), "{}", error);
+    }
+
+    #[test]
+    fn owned_builder() {
+        let _jvm = JvmBuilder::new().build().unwrap();
+        let entries = vec!["first.jar".to_string(), "second.jar".to_string()];
+        let mut builder = JvmBuilder::new();
+        for entry in &entries {
+            builder.classpath_entry(ClasspathEntry::new(entry));
+        }
+        builder
+            .java_opt(JavaOpt::new("-Dj4rs.owned.builder=true"))
+            .with_attach_options(AttachOptions::new().thread_name("owned-builder"))
+            .with_min_java_version(8);
+
+        let json = serde_json::to_value(&builder).unwrap();
+        assert_eq!(json["classpath_entries"], serde_json::json!(["first.jar", "second.jar"]));
+        assert_eq!(json["java_opts"], serde_json::json!(["-Dj4rs.owned.builder=true"]));
+        assert_eq!(json["attach_options"]["thread_name"], "owned-builder");
+        assert_eq!(json["min_java_version"], 8);
+        assert!(format!("{:?}", builder).contains("second.jar"));
+
+        // The same settings build Jvms on several threads
+        let reused = builder.clone();
+        let handle = std::thread::spawn(move || reused.build().is_ok());
+        assert!(builder.build().is_ok());
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn agents() {
+        let _jvm = JvmBuilder::new().build().unwrap();
+        let base = std::env::temp_dir().join("j4rs_agents");
+        let jassets = base.join("jassets");
+        fs::create_dir_all(&jassets).unwrap();
+        fs::create_dir_all(base.join("deps")).unwrap();
+        fs::write(jassets.join("agent.jar"), "").unwrap();
+        fs::write(base.join("deps").join("libagent.so"), "").unwrap();
+        let java_opt = |agent: Agent| agent.java_opt(&jassets, &[]);
+
+        let agent_jar = jassets.join("agent.jar").to_str().unwrap().to_string();
+        let native_lib = base.join("deps").join("libagent.so").to_str().unwrap().to_string();
+        assert_eq!(java_opt(Agent::Java { jar: AgentJar::Path("agent.jar".to_string()), options: None }).unwrap(),
+                   format!("-javaagent:{}", agent_jar));
+        assert_eq!(java_opt(Agent::Java { jar: AgentJar::Path(agent_jar.clone()), options: Some("a=b,c".to_string()) }).unwrap(),
+                   format!("-javaagent:{}=a=b,c", agent_jar));
+        assert_eq!(java_opt(Agent::Native { path: "deps/libagent.so".to_string(), options: Some("port=5005".to_string()) }).unwrap(),
+                   format!("-agentpath:{}=port=5005", native_lib));
+        let artifact = MavenArtifact::from("io.github.astonbitecode:agent:1.0");
+        fs::write(jassets.join("agent-1.0.jar"), "").unwrap();
+        assert!(java_opt(Agent::Java { jar: AgentJar::Artifact(artifact), options: None }).unwrap().ends_with("agent-1.0.jar"));
+
+        let error = java_opt(Agent::Java { jar: AgentJar::Path("missing.jar".to_string()), options: None }).err().unwrap();
+        assert!(format!("{}", error).contains("missing.jar"));
+        let error = JvmBuilder::new().native_agent("/not/existing/libagent.so", None).build().err().unwrap();
+        assert!(format!("{}", error).contains("/not/existing/libagent.so"));
+
+        // The agents are Java options, that are loaded first
+        let java_opts = JvmBuilder::new()
+            .java_opt(JavaOpt::new("-Dj4rs.agents=true"))
+            .java_agent(&agent_jar, Some("verbose"))
+            .native_agent(&native_lib, None)
+            .effective_java_opts()
+            .unwrap();
+        assert_eq!(java_opts, vec![
+            format!("-javaagent:{}=verbose", agent_jar),
+            format!("-agentpath:{}", native_lib),
+            "-Dj4rs.agents=true".to_string(),
+        ]);
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn module_settings() {
+        let _jvm = JvmBuilder::new().build().unwrap();
+        let dir = std::env::temp_dir().join("j4rs_module_settings");
+        fs::create_dir_all(&dir).unwrap();
+        let modules_dir = dir.to_str().unwrap();
+        let missing = format!("{}/missing.jar", modules_dir);
+        let agent_jar = dir.join("agent.jar").to_str().unwrap().to_string();
+        fs::write(&agent_jar, "").unwrap();
+
+        assert!(JvmBuilder::new().module_opts().unwrap().is_empty());
+        let mut builder = JvmBuilder::new();
+        builder
+            .module_path_entry(modules_dir)
+            .module_path_entry(&missing)
+            .add_opens("java.base", "java.lang")
+            .add_exports("java.base", "sun.nio.ch");
+        assert_eq!(builder.module_opts().unwrap(), vec![
+            format!("--module-path={}", modules_dir),
+            "--add-modules=ALL-MODULE-PATH".to_string(),
+            "--add-opens=java.base/java.lang=ALL-UNNAMED".to_string(),
+            "--add-exports=java.base/sun.nio.ch=ALL-UNNAMED".to_string(),
+        ]);
+        builder.add_module("java.sql").add_module("java.xml");
+        assert_eq!(builder.module_opts().unwrap()[1], "--add-modules=java.sql,java.xml");
+        let error = builder.fail_on_missing_classpath_entries().module_opts().err().unwrap();
+        assert!(format!("{}", error).contains(&missing));
+
+        // The module settings are Java options, that follow the agents
+        let java_opts = JvmBuilder::new()
+            .java_opt(JavaOpt::new("-Dj4rs.modules=true"))
+            .add_opens("java.base", "java.util")
+            .java_agent(&agent_jar, None)
+            .effective_java_opts()
+            .unwrap();
+        assert_eq!(java_opts, vec![
+            format!("-javaagent:{}", agent_jar),
+            "--add-opens=java.base/java.util=ALL-UNNAMED".to_string(),
+            "-Dj4rs.modules=true".to_string(),
+        ]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn classpath_entries_resolution() {
+        let jvm = JvmBuilder::new().build().unwrap();
+        let dir = std::env::temp_dir().join("j4rs_classpath_entries_resolution_builder");
+        let lib = dir.join("lib");
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(lib.join("one.jar"), "").unwrap();
+        fs::write(lib.join("two.jar"), "").unwrap();
+        let lib_str = lib.to_str().unwrap();
+        let one = lib.join("one.jar").to_str().unwrap().to_string();
+        let two = lib.join("two.jar").to_str().unwrap().to_string();
+        let wildcard = format!("{}/*", lib_str);
+        let missing = format!("{}/missing.jar", lib_str);
+
+        // Patterns are resolved to jars, the duplicates are removed and the missing entries are left out
+        let classpath = JvmBuilder::new()
+            .with_no_implicit_classpath()
+            .classpath_entry(ClasspathEntry::new(&wildcard))
+            .classpath_entry(ClasspathEntry::new(&one))
+            .classpath_entry(ClasspathEntry::new(&missing))
+            .resolved_classpath()
+            .unwrap();
+        assert_eq!(classpath, vec![".".to_string(), one.clone(), two]);
+
+        // The implicit classpath precedes the entries of the builder
+        let classpath = JvmBuilder::new()
+            .classpath_entry(ClasspathEntry::new(lib_str))
+            .resolved_classpath()
+            .unwrap();
+        assert!(classpath.iter().any(|entry| entry.contains(&format!("j4rs-{}-jar-with-dependencies.jar", j4rs_version()))));
+        assert!(classpath.ends_with(&[lib_str.to_string(), one, lib.join("two.jar").to_str().unwrap().to_string()]));
+
+        let res = JvmBuilder::new()
+            .classpath_entry(ClasspathEntry::new(&missing))
+            .fail_on_missing_classpath_entries()
+            .build();
+        let error = format!("{}", res.err().unwrap());
+        assert!(error.contains(&missing), "{}", error);
+
+        assert!(!jvm.classpath().unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn external_builder_config() {
+        let _jvm = JvmBuilder::new().build().unwrap();
+        let dir = std::env::temp_dir().join("j4rs_external_builder_config");
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("j4rs.toml");
+        let config_jar = dir.join("from_config.jar");
+        fs::write(&config_jar, "").unwrap();
+        fs::write(&config_path, format!("classpath = [{:?}]\njava_opts = [\"-Dj4rs.from.config=true\"]\n", config_jar.to_str().unwrap())).unwrap();
+        let invalid_path = dir.join("invalid.json");
+        fs::write(&invalid_path, "{\"java_opts\": \"-Xmx1g\"}").unwrap();
+
+        // The settings of the file are merged into the ones of the builder
+        let res = JvmBuilder::new()
+            .java_opt(JavaOpt::new("-Dj4rs.from.builder=true"))
+            .with_config_file(config_path.to_str().unwrap())
+            .fail_on_ignored_options()
+            .build();
+        let error = format!("{}", res.err().unwrap());
+        assert!(error.contains("from_config.jar"), "{}", error);
+        assert!(error.contains("[-Dj4rs.from.builder=true, -Dj4rs.from.config=true]"), "{}", error);
+
+        let res = JvmBuilder::new()
+            .with_config_file(invalid_path.to_str().unwrap())
+            .build();
+        assert!(format!("{}", res.err().unwrap()).contains("Invalid j4rs configuration file"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn new_invocation_arg() {
         let _jvm = JvmBuilder::new().build().unwrap();
@@ -11,21 +11,26 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use std::fs;
 use std::os::raw::c_void;
-use std::path::MAIN_SEPARATOR;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use java_locator::{get_jvm_dyn_lib_file_name, locate_jvm_dyn_library};
 use jni_sys::{
     JavaVM,
     jclass,
     jint,
+    JNI_ERR,
     JNIEnv,
     jsize,
 };
 use libloading;
 
 use crate::{utils, errors};
-use crate::errors::opt_to_res;
+use crate::api_tweaks::JvmLibLocation;
+use crate::errors::{J4RsError, opt_to_res};
+use crate::logger::{error, info, warn};
 
 type JNIGetCreatedJavaVMs = unsafe extern "system" fn(vmBuf: *mut *mut JavaVM, bufLen: jsize, nVMs: *mut jsize) -> jint;
 
@@ -35,28 +40,129 @@ type JNICreateJavaVM = unsafe extern "system" fn(
     args: *mut c_void,
 ) -> jint;
 
+// How deep in a Java home the jvm dynamic library is searched
+const JAVA_HOME_SEARCH_DEPTH: usize = 5;
+
 lazy_static! {
-    static ref JVM_LIB: libloading::Library = {
-        let full_path = format!("{}{}{}",
-            locate_jvm_dyn_library().expect("Could find the jvm dynamic library"),
-            MAIN_SEPARATOR,
-            get_jvm_dyn_lib_file_name()
-        );
-        libloading::Library::new(full_path).expect("Could not load the jvm dynamic library")
-    };
+    // The loaded jvm dynamic library. Once loaded, it is never unloaded.
+    static ref JVM_LIB: Mutex<Option<Arc<JvmLib>>> = Mutex::new(None);
+}
 
-    static ref GET_CREATED_JVMS: libloading::Symbol<'static, JNIGetCreatedJavaVMs> = unsafe {
-        JVM_LIB.get(b"JNI_GetCreatedJavaVMs").expect("Could not find symbol: JNI_GetCreatedJavaVMs")
-    };
+struct JvmLib {
+    path: PathBuf,
+    get_created_jvms: JNIGetCreatedJavaVMs,
+    create_jvm: JNICreateJavaVM,
+    // Keeps the functions above valid
+    _library: libloading::Library,
+}
+
+impl JvmLib {
+    fn load(path: PathBuf) -> errors::Result<JvmLib> {
+        info(&format!("Loading the jvm dynamic library {}", path.display()));
+        let library = libloading::Library::new(&path)
+            .map_err(|error| J4RsError::GeneralError(format!("Could not load the jvm dynamic library {}: {}", path.display(), error)))?;
+        let (get_created_jvms, create_jvm) = unsafe {
+            let get_created_jvms: libloading::Symbol<JNIGetCreatedJavaVMs> = library.get(b"JNI_GetCreatedJavaVMs")
+                .map_err(|error| J4RsError::GeneralError(format!("Could not find the symbol JNI_GetCreatedJavaVMs in {}: {}", path.display(), error)))?;
+            let create_jvm: libloading::Symbol<JNICreateJavaVM> = library.get(b"JNI_CreateJavaVM")
+                .map_err(|error| J4RsError::GeneralError(format!("Could not find the symbol JNI_CreateJavaVM in {}: {}", path.display(), error)))?;
+            (*get_created_jvms, *create_jvm)
+        };
+        Ok(JvmLib { path, get_created_jvms, create_jvm, _library: library })
+    }
+}
+
+/// Loads the jvm dynamic library from the provided location, or from the default one if no location is provided.
+///
+/// Only one jvm dynamic library is loaded per process. If it is already loaded, a different requested location is ignored.
+pub(crate) fn load_jvm_lib(location: Option<&JvmLibLocation>) -> errors::Result<()> {
+    jvm_lib(location).map(|_| ())
+}
 
-    static ref CREATE_JVM: libloading::Symbol<'static, JNICreateJavaVM> = unsafe {
-        JVM_LIB.get(b"JNI_CreateJavaVM").expect("Could not find symbol: JNI_CreateJavaVM")
+fn jvm_lib(location: Option<&JvmLibLocation>) -> errors::Result<Arc<JvmLib>> {
+    let mut jvm_lib = JVM_LIB.lock()?;
+    if let Some(loaded) = jvm_lib.as_ref() {
+        let ignored = match location {
+            Some(JvmLibLocation::JavaHome(java_home)) => !loaded.path.starts_with(java_home),
+            Some(JvmLibLocation::LibPath(lib_path)) => !loaded.path.starts_with(lib_path),
+            None => false,
+        };
+        if ignored {
+            warn(&format!("The jvm dynamic library {} is already loaded. Ignoring the requested {}", loaded.path.display(), location.unwrap()));
+        }
+        return Ok(loaded.clone());
+    }
+    let path = match location {
+        Some(location) => jvm_lib_path(location)?,
+        None => default_jvm_lib_path()?,
     };
+    let loaded = Arc::new(JvmLib::load(path)?);
+    *jvm_lib = Some(loaded.clone());
+    Ok(loaded)
+}
+
+// Finds the jvm dynamic library file of a location
+pub(crate) fn jvm_lib_path(location: &JvmLibLocation) -> errors::Result<PathBuf> {
+    match location {
+        JvmLibLocation::JavaHome(java_home) => {
+            if !java_home.is_dir() {
+                return Err(J4RsError::GeneralError(format!("The Java home {} is not a directory", java_home.display())));
+            }
+            let mut found = Vec::new();
+            find_files(java_home, get_jvm_dyn_lib_file_name(), JAVA_HOME_SEARCH_DEPTH, &mut found);
+            // The server VM is preferred
+            found.sort_by_key(|path| !path.components().any(|component| component.as_os_str() == "server"));
+            found.into_iter().next()
+                .ok_or_else(|| J4RsError::GeneralError(format!("Could not find the jvm dynamic library {} in the Java home {}", get_jvm_dyn_lib_file_name(), java_home.display())))
+        }
+        JvmLibLocation::LibPath(lib_path) => {
+            let lib_path = if lib_path.is_dir() {
+                lib_path.join(get_jvm_dyn_lib_file_name())
+            } else {
+                lib_path.clone()
+            };
+            if lib_path.is_file() {
+                Ok(lib_path)
+            } else {
+                Err(J4RsError::GeneralError(format!("The jvm dynamic library {} does not exist", lib_path.display())))
+            }
+        }
+    }
+}
+
+fn default_jvm_lib_path() -> errors::Result<PathBuf> {
+    locate_jvm_dyn_library()
+        .map(|dir| Path::new(&dir).join(get_jvm_dyn_lib_file_name()))
+        .map_err(|error| J4RsError::GeneralError(format!(
+            "Could not find the jvm dynamic library. Please set the JAVA_HOME, or use JvmBuilder::with_java_home or JvmBuilder::with_jvm_lib: {}", error)))
+}
+
+fn find_files(dir: &Path, file_name: &str, depth: usize, found: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if path.is_dir() {
+                if depth > 0 {
+                    find_files(&path, file_name, depth - 1, found);
+                }
+            } else if path.file_name().map(|name| name == file_name).unwrap_or(false) {
+                found.push(path);
+            }
+        }
+    }
 }
 
 pub(crate) fn get_created_java_vms(vm_buf: &mut Vec<*mut JavaVM>, buf_len: jsize, n_vms: *mut jsize) -> jint {
-    unsafe {
-        GET_CREATED_JVMS(vm_buf.as_mut_ptr(), buf_len, n_vms)
+    match jvm_lib(None) {
+        Ok(jvm_lib) => unsafe {
+            (jvm_lib.get_created_jvms)(vm_buf.as_mut_ptr(), buf_len, n_vms)
+        },
+        Err(err) => {
+            error(&format!("Cannot retrieve the created JVMs: {}", err));
+            if !n_vms.is_null() {
+                unsafe { *n_vms = 0 };
+            }
+            JNI_ERR
+        }
     }
 }
 
@@ -65,8 +171,14 @@ pub(crate) fn create_java_vm(
     penv: *mut *mut c_void,
     args: *mut c_void,
 ) -> jint {
-    unsafe {
-        CREATE_JVM(jvm, penv, args)
+    match jvm_lib(None) {
+        Ok(jvm_lib) => unsafe {
+            (jvm_lib.create_jvm)(jvm, penv, args)
+        },
+        Err(err) => {
+            error(&format!("Cannot create a JVM: {}", err));
+            JNI_ERR
+        }
     }
 }
 
@@ -81,4 +193,22 @@ pub(crate) fn find_class(env: *mut JNIEnv, classname: &str) -> errors::Result<jc
         utils::drop_c_string(cstr);
         Ok(jc)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod generic_unit_tests {
+    use super::*;
+
+    #[test]
+    fn jvm_lib_paths() {
+        let java_home = PathBuf::from(java_locator::locate_java_home().unwrap());
+        let found = jvm_lib_path(&JvmLibLocation::JavaHome(java_home.clone())).unwrap();
+        assert!(found.is_file());
+        assert!(found.starts_with(&java_home));
+        assert_eq!(jvm_lib_path(&JvmLibLocation::LibPath(found.clone())).unwrap(), found);
+        assert_eq!(jvm_lib_path(&JvmLibLocation::LibPath(found.parent().unwrap().to_path_buf())).unwrap(), found);
+
+        assert!(jvm_lib_path(&JvmLibLocation::JavaHome(PathBuf::from("/not/existing/java"))).is_err());
+        assert!(jvm_lib_path(&JvmLibLocation::LibPath(PathBuf::from("/not/existing/libjvm.so"))).is_err());
+    }
+}
@@ -13,9 +13,32 @@ use std::os::raw::c_void;
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use std::fmt;
+use std::path::PathBuf;
+
 use jni_sys::{JavaVM, jclass, jint, JNIEnv, jsize};
+use serde::Serialize;
+
 use crate::errors;
 
+/// The location of the jvm dynamic library (`libjvm.so`, `libjvm.dylib` or `jvm.dll`) to load.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) enum JvmLibLocation {
+    /// A Java installation, in which the library is searched.
+    JavaHome(PathBuf),
+    /// The library file, or the directory that contains it.
+    LibPath(PathBuf),
+}
+
+impl fmt::Display for JvmLibLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JvmLibLocation::JavaHome(java_home) => write!(f, "Java home {}", java_home.display()),
+            JvmLibLocation::LibPath(lib_path) => write!(f, "jvm dynamic library {}", lib_path.display()),
+        }
+    }
+}
+
 #[cfg(not(any(target_os = "android")))]
 mod generic;
 
@@ -27,6 +50,11 @@ pub fn get_created_java_vms(vm_buf: &mut Vec<*mut JavaVM>, buf_len: jsize, n_vms
 #[cfg(not(any(target_os = "android")))]
 pub fn set_java_vm(_: *mut JavaVM) {}
 
+#[cfg(not(any(target_os = "android")))]
+pub(crate) fn load_jvm_lib(location: Option<&JvmLibLocation>) -> errors::Result<()> {
+    generic::load_jvm_lib(location)
+}
+
 #[cfg(not(any(target_os = "android")))]
 pub fn create_java_vm(
     pvm: *mut *mut JavaVM,
@@ -54,6 +82,12 @@ pub fn set_java_vm(java_vm: *mut JavaVM) {
     android::set_java_vm(java_vm);
 }
 
+// In Android, the JVM is provided by the system
+#[cfg(target_os = "android")]
+pub(crate) fn load_jvm_lib(_: Option<&JvmLibLocation>) -> errors::Result<()> {
+    Ok(())
+}
+
 #[cfg(target_os = "android")]
 pub fn create_java_vm(
     pvm: *mut *mut JavaVM,
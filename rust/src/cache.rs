@@ -15,11 +15,14 @@
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use jni_sys::{
     self,
     jboolean,
+    jchar,
     jclass,
+    jint,
     jmethodID,
     JNIEnv,
     jobject,
@@ -38,19 +41,20 @@ pub(crate) const INVO_BASE_NAME: &'static str = "org/astonbitecode/j4rs/api/Nati
 pub(crate) const INVO_IFACE_NAME: &'static str = "org/astonbitecode/j4rs/api/NativeInvocation";
 pub(crate) const UNKNOWN_FOR_RUST: &'static str = "known_in_java_world";
 pub(crate) const J4RS_ARRAY: &'static str = "org.astonbitecode.j4rs.api.dtos.Array";
+pub(crate) const BATCH_ITERATOR: &str = "org.astonbitecode.j4rs.utils.BatchIterator";
 
 pub(crate) type JniGetMethodId = unsafe extern "system" fn(*mut *const jni_sys::JNINativeInterface_, *mut jni_sys::_jobject, *const c_char, *const c_char) -> *mut jni_sys::_jmethodID;
 pub(crate) type JniGetStaticMethodId = unsafe extern "system" fn(*mut *const jni_sys::JNINativeInterface_, *mut jni_sys::_jobject, *const c_char, *const c_char) -> *mut jni_sys::_jmethodID;
 #[allow(non_snake_case)]
 pub(crate) type JniNewObject = unsafe extern "C" fn(env: *mut JNIEnv, clazz: jclass, methodID: jmethodID, ...) -> jobject;
-pub(crate) type JniNewStringUTF = unsafe extern "system" fn(env: *mut JNIEnv, utf: *const c_char) -> jstring;
-#[allow(non_snake_case)]
-pub(crate) type JniGetStringUTFChars = unsafe extern "system" fn(env: *mut JNIEnv, str: jstring, isCopy: *mut jboolean) -> *const c_char;
-#[allow(non_snake_case)]
-pub(crate) type JniReleaseStringUTFChars = unsafe extern "system" fn(env: *mut JNIEnv, str: jstring, utf: *const c_char);
+pub(crate) type JniNewString = unsafe extern "system" fn(env: *mut JNIEnv, unicode: *const jchar, len: jsize) -> jstring;
+pub(crate) type JniGetStringLength = unsafe extern "system" fn(env: *mut JNIEnv, str: jstring) -> jsize;
+pub(crate) type JniGetStringRegion = unsafe extern "system" fn(env: *mut JNIEnv, str: jstring, start: jsize, len: jsize, buf: *mut jchar);
 #[allow(non_snake_case)]
 pub(crate) type JniCallObjectMethod = unsafe extern "C" fn(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, ...) -> jobject;
 #[allow(non_snake_case)]
+pub(crate) type JniCallBooleanMethod = unsafe extern "C" fn(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, ...) -> jboolean;
+#[allow(non_snake_case)]
 pub(crate) type JniCallVoidMethod = unsafe extern "C" fn(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, ...);
 pub(crate) type JniCallStaticObjectMethod = unsafe extern "C" fn(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, ...) -> jobject;
 pub(crate) type JniNewObjectArray = unsafe extern "system" fn(env: *mut JNIEnv, len: jsize, clazz: jclass, init: jobject) -> jobjectArray;
@@ -61,6 +65,11 @@ pub(crate) type JniExceptionClear = unsafe extern "system" fn(_: *mut JNIEnv);
 pub(crate) type JniDeleteLocalRef = unsafe extern "system" fn(_: *mut JNIEnv, _: jobject) -> ();
 pub(crate) type JniDeleteGlobalRef = unsafe extern "system" fn(_: *mut JNIEnv, _: jobject) -> ();
 pub(crate) type JniNewGlobalRef = unsafe extern "system" fn(_: *mut JNIEnv, _: jobject) -> jobject;
+pub(crate) type JniPushLocalFrame = unsafe extern "system" fn(_: *mut JNIEnv, _: jint) -> jint;
+pub(crate) type JniPopLocalFrame = unsafe extern "system" fn(_: *mut JNIEnv, _: jobject) -> jobject;
+pub(crate) type JniEnsureLocalCapacity = unsafe extern "system" fn(_: *mut JNIEnv, _: jint) -> jint;
+pub(crate) type JniMonitorEnter = unsafe extern "system" fn(_: *mut JNIEnv, _: jobject) -> jint;
+pub(crate) type JniMonitorExit = unsafe extern "system" fn(_: *mut JNIEnv, _: jobject) -> jint;
 
 const CLASS_CACHING_ENABLED: bool = !(cfg!(target_os = "android"));
 
@@ -69,18 +78,24 @@ lazy_static! {
     pub(crate) static ref MUTEX: Mutex<bool> = Mutex::new(false);
     // If a Jvm is created with defining a jassets_path other than the default, this is set here
     pub(crate) static ref JASSETS_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+    // The name of the native library that was passed to the Java world when j4rs created the JVM
+    pub(crate) static ref NATIVE_LIB_NAME: Mutex<Option<String>> = Mutex::new(None);
 }
 
+// Whether the JVM of the process has been destroyed
+static JVM_DESTROYED: AtomicBool = AtomicBool::new(false);
+
 thread_local! {
     pub(crate) static JNI_ENV: RefCell<Option<*mut JNIEnv>> = RefCell::new(None);
     pub(crate) static ACTIVE_JVMS: RefCell<i32> = RefCell::new(0);
     pub(crate) static JNI_GET_METHOD_ID: RefCell<Option<JniGetMethodId>> = RefCell::new(None);
     pub(crate) static JNI_GET_STATIC_METHOD_ID: RefCell<Option<JniGetStaticMethodId>> = RefCell::new(None);
     pub(crate) static JNI_NEW_OBJECT: RefCell<Option<JniNewObject>> = RefCell::new(None);
-    pub(crate) static JNI_NEW_STRING_UTF: RefCell<Option<JniNewStringUTF>> = RefCell::new(None);
-    pub(crate) static JNI_GET_STRING_UTF_CHARS: RefCell<Option<JniGetStringUTFChars>> = RefCell::new(None);
-    pub(crate) static JNI_RELEASE_STRING_UTF_CHARS: RefCell<Option<JniReleaseStringUTFChars>> = RefCell::new(None);
+    pub(crate) static JNI_NEW_STRING: RefCell<Option<JniNewString>> = RefCell::new(None);
+    pub(crate) static JNI_GET_STRING_LENGTH: RefCell<Option<JniGetStringLength>> = RefCell::new(None);
+    pub(crate) static JNI_GET_STRING_REGION: RefCell<Option<JniGetStringRegion>> = RefCell::new(None);
     pub(crate) static JNI_CALL_OBJECT_METHOD: RefCell<Option<JniCallObjectMethod>> = RefCell::new(None);
+    pub(crate) static JNI_CALL_BOOLEAN_METHOD: RefCell<Option<JniCallBooleanMethod>> = RefCell::new(None);
     pub(crate) static JNI_CALL_VOID_METHOD: RefCell<Option<JniCallVoidMethod>> = RefCell::new(None);
     pub(crate) static JNI_CALL_STATIC_OBJECT_METHOD: RefCell<Option<JniCallStaticObjectMethod>> = RefCell::new(None);
     pub(crate) static JNI_NEW_OBJECT_ARRAY: RefCell<Option<JniNewObjectArray>> = RefCell::new(None);
@@ -91,6 +106,11 @@ thread_local! {
     pub(crate) static JNI_DELETE_LOCAL_REF: RefCell<Option<JniDeleteLocalRef>> = RefCell::new(None);
     pub(crate) static JNI_DELETE_GLOBAL_REF: RefCell<Option<JniDeleteGlobalRef>> = RefCell::new(None);
     pub(crate) static JNI_NEW_GLOBAL_REF: RefCell<Option<JniNewGlobalRef>> = RefCell::new(None);
+    pub(crate) static JNI_PUSH_LOCAL_FRAME: RefCell<Option<JniPushLocalFrame>> = RefCell::new(None);
+    pub(crate) static JNI_POP_LOCAL_FRAME: RefCell<Option<JniPopLocalFrame>> = RefCell::new(None);
+    pub(crate) static JNI_ENSURE_LOCAL_CAPACITY: RefCell<Option<JniEnsureLocalCapacity>> = RefCell::new(None);
+    pub(crate) static JNI_MONITOR_ENTER: RefCell<Option<JniMonitorEnter>> = RefCell::new(None);
+    pub(crate) static JNI_MONITOR_EXIT: RefCell<Option<JniMonitorExit>> = RefCell::new(None);
     // This is the factory class. It creates instances using reflection. Currently the `NativeInstantiationImpl`.
     pub(crate) static FACTORY_CLASS: RefCell<Option<jclass>> = RefCell::new(None);
     // The constructor method of the `NativeInstantiationImpl`.
@@ -126,8 +146,15 @@ thread_local! {
     pub(crate) static CLONE_STATIC_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
     // The cast method
     pub(crate) static CAST_STATIC_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
+    // The wrap method
+    pub(crate) static WRAP_STATIC_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
     // The get json method
     pub(crate) static GET_JSON_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
+    // The is null method
+    pub(crate) static IS_NULL_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
+    pub(crate) static GET_OBJECT_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
+    pub(crate) static GET_OBJECT_CLASS_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
+    pub(crate) static CLASS_GET_NAME_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
     // The invstatic ocation argument constructor method for objects created by Java
     pub(crate) static INV_ARG_JAVA_CONSTRUCTOR_METHOD: RefCell<Option<jmethodID>> = RefCell::new(None);
     // The invstatic ocation argument constructor method for objects created by Rust
@@ -163,6 +190,8 @@ macro_rules! get_cached {
                 let j = {$do_retrieve};
                 if CLASS_CACHING_ENABLED {
                     $setter_name(j);
+                    // The cached references live as long as the thread and are not reported as leaks
+                    crate::diagnostics::untrack_global_ref(j as jobject);
                 }
                 Ok(j)
             } else {
@@ -172,6 +201,24 @@ macro_rules! get_cached {
     };
 }
 
+pub(crate) fn set_jvm_destroyed() {
+    debug("Called set_jvm_destroyed");
+    JVM_DESTROYED.store(true, Ordering::SeqCst);
+}
+
+pub(crate) fn is_jvm_destroyed() -> bool {
+    JVM_DESTROYED.load(Ordering::SeqCst)
+}
+
+/// Returns an error if the JVM has been destroyed, in order to avoid using invalid JNI pointers.
+pub(crate) fn ensure_jvm_not_destroyed() -> errors::Result<()> {
+    if is_jvm_destroyed() {
+        Err(errors::J4RsError::JniError("The JVM has been destroyed and cannot be used anymore".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
 pub(crate) fn add_active_jvm() {
     ACTIVE_JVMS.with(|active_jvms| {
         let active_number = {
@@ -256,44 +303,44 @@ pub(crate) fn get_jni_new_object() -> Option<JniNewObject> {
     })
 }
 
-pub(crate) fn set_jni_new_string_utf(j: Option<JniNewStringUTF>) -> Option<JniNewStringUTF> {
-    debug("Called set_jni_new_string_utf");
-    JNI_NEW_STRING_UTF.with(|opt| {
+pub(crate) fn set_jni_new_string(j: Option<JniNewString>) -> Option<JniNewString> {
+    debug("Called set_jni_new_string");
+    JNI_NEW_STRING.with(|opt| {
         *opt.borrow_mut() = j;
     });
-    get_jni_new_string_utf()
+    get_jni_new_string()
 }
 
-pub(crate) fn get_jni_new_string_utf() -> Option<JniNewStringUTF> {
-    JNI_NEW_STRING_UTF.with(|opt| {
+pub(crate) fn get_jni_new_string() -> Option<JniNewString> {
+    JNI_NEW_STRING.with(|opt| {
         *opt.borrow()
     })
 }
 
-pub(crate) fn set_jni_get_string_utf_chars(j: Option<JniGetStringUTFChars>) -> Option<JniGetStringUTFChars> {
-    debug("Called set_jni_get_string_utf_chars");
-    JNI_GET_STRING_UTF_CHARS.with(|opt| {
+pub(crate) fn set_jni_get_string_length(j: Option<JniGetStringLength>) -> Option<JniGetStringLength> {
+    debug("Called set_jni_get_string_length");
+    JNI_GET_STRING_LENGTH.with(|opt| {
         *opt.borrow_mut() = j;
     });
-    get_jni_get_string_utf_chars()
+    get_jni_get_string_length()
 }
 
-pub(crate) fn get_jni_get_string_utf_chars() -> Option<JniGetStringUTFChars> {
-    JNI_GET_STRING_UTF_CHARS.with(|opt| {
+pub(crate) fn get_jni_get_string_length() -> Option<JniGetStringLength> {
+    JNI_GET_STRING_LENGTH.with(|opt| {
         *opt.borrow()
     })
 }
 
-pub(crate) fn set_jni_release_string_utf_chars(j: Option<JniReleaseStringUTFChars>) -> Option<JniReleaseStringUTFChars> {
-    debug("Called set_jni_release_string_utf_chars");
-    JNI_RELEASE_STRING_UTF_CHARS.with(|opt| {
+pub(crate) fn set_jni_get_string_region(j: Option<JniGetStringRegion>) -> Option<JniGetStringRegion> {
+    debug("Called set_jni_get_string_region");
+    JNI_GET_STRING_REGION.with(|opt| {
         *opt.borrow_mut() = j;
     });
-    get_jni_release_string_utf_chars()
+    get_jni_get_string_region()
 }
 
-pub(crate) fn get_jni_release_string_utf_chars() -> Option<JniReleaseStringUTFChars> {
-    JNI_RELEASE_STRING_UTF_CHARS.with(|opt| {
+pub(crate) fn get_jni_get_string_region() -> Option<JniGetStringRegion> {
+    JNI_GET_STRING_REGION.with(|opt| {
         *opt.borrow()
     })
 }
@@ -312,6 +359,20 @@ pub(crate) fn get_jni_call_object_method() -> Option<JniCallObjectMethod> {
     })
 }
 
+pub(crate) fn set_jni_call_boolean_method(j: Option<JniCallBooleanMethod>) -> Option<JniCallBooleanMethod> {
+    debug("Called set_jni_call_boolean_method");
+    JNI_CALL_BOOLEAN_METHOD.with(|opt| {
+        *opt.borrow_mut() = j;
+    });
+    get_jni_call_boolean_method()
+}
+
+pub(crate) fn get_jni_call_boolean_method() -> Option<JniCallBooleanMethod> {
+    JNI_CALL_BOOLEAN_METHOD.with(|opt| {
+        *opt.borrow()
+    })
+}
+
 pub(crate) fn set_jni_call_void_method(j: Option<JniCallVoidMethod>) -> Option<JniCallVoidMethod> {
     debug("Called set_jni_call_void_method");
     JNI_CALL_VOID_METHOD.with(|opt| {
@@ -453,6 +514,76 @@ pub(crate) fn get_jni_new_global_ref() -> Option<JniNewGlobalRef> {
     })
 }
 
+pub(crate) fn set_jni_push_local_frame(j: Option<JniPushLocalFrame>) -> Option<JniPushLocalFrame> {
+    debug("Called set_jni_push_local_frame");
+    JNI_PUSH_LOCAL_FRAME.with(|opt| {
+        *opt.borrow_mut() = j;
+    });
+    get_jni_push_local_frame()
+}
+
+pub(crate) fn get_jni_push_local_frame() -> Option<JniPushLocalFrame> {
+    JNI_PUSH_LOCAL_FRAME.with(|opt| {
+        *opt.borrow()
+    })
+}
+
+pub(crate) fn set_jni_pop_local_frame(j: Option<JniPopLocalFrame>) -> Option<JniPopLocalFrame> {
+    debug("Called set_jni_pop_local_frame");
+    JNI_POP_LOCAL_FRAME.with(|opt| {
+        *opt.borrow_mut() = j;
+    });
+    get_jni_pop_local_frame()
+}
+
+pub(crate) fn get_jni_pop_local_frame() -> Option<JniPopLocalFrame> {
+    JNI_POP_LOCAL_FRAME.with(|opt| {
+        *opt.borrow()
+    })
+}
+
+pub(crate) fn set_jni_ensure_local_capacity(j: Option<JniEnsureLocalCapacity>) -> Option<JniEnsureLocalCapacity> {
+    debug("Called set_jni_ensure_local_capacity");
+    JNI_ENSURE_LOCAL_CAPACITY.with(|opt| {
+        *opt.borrow_mut() = j;
+    });
+    get_jni_ensure_local_capacity()
+}
+
+pub(crate) fn get_jni_ensure_local_capacity() -> Option<JniEnsureLocalCapacity> {
+    JNI_ENSURE_LOCAL_CAPACITY.with(|opt| {
+        *opt.borrow()
+    })
+}
+
+pub(crate) fn set_jni_monitor_enter(j: Option<JniMonitorEnter>) -> Option<JniMonitorEnter> {
+    debug("Called set_jni_monitor_enter");
+    JNI_MONITOR_ENTER.with(|opt| {
+        *opt.borrow_mut() = j;
+    });
+    get_jni_monitor_enter()
+}
+
+pub(crate) fn get_jni_monitor_enter() -> Option<JniMonitorEnter> {
+    JNI_MONITOR_ENTER.with(|opt| {
+        *opt.borrow()
+    })
+}
+
+pub(crate) fn set_jni_monitor_exit(j: Option<JniMonitorExit>) -> Option<JniMonitorExit> {
+    debug("Called set_jni_monitor_exit");
+    JNI_MONITOR_EXIT.with(|opt| {
+        *opt.borrow_mut() = j;
+    });
+    get_jni_monitor_exit()
+}
+
+pub(crate) fn get_jni_monitor_exit() -> Option<JniMonitorExit> {
+    JNI_MONITOR_EXIT.with(|opt| {
+        *opt.borrow()
+    })
+}
+
 pub(crate) fn set_factory_class(j: jclass) {
     debug("Called set_factory_class");
     FACTORY_CLASS.with(|opt| {
@@ -946,6 +1077,41 @@ pub(crate) fn get_cast_static_method() -> errors::Result<jmethodID> {
         set_cast_static_method)
 }
 
+pub(crate) fn set_wrap_static_method(j: jmethodID) {
+    debug("Called set_wrap_static_method");
+    WRAP_STATIC_METHOD.with(|opt| {
+        *opt.borrow_mut() = Some(j);
+    });
+}
+
+pub(crate) fn get_wrap_static_method() -> errors::Result<jmethodID> {
+    get_cached!(
+        WRAP_STATIC_METHOD,
+        {
+            let env = get_thread_local_env()?;
+
+            let wrap_method_signature = format!(
+                "(Ljava/lang/Object;Ljava/lang/Class;)L{};",
+                INVO_IFACE_NAME);
+            let cstr1 = utils::to_c_string("wrap");
+            let cstr2 = utils::to_c_string(wrap_method_signature.as_ref());
+            // Get the method ID for the `NativeInvocation.wrap`
+            let j = unsafe {
+                (opt_to_res(get_jni_get_static_method_id())?)(
+                    env,
+                    get_class_to_invoke_clone_and_cast()?,
+                    cstr1,
+                    cstr2,
+                )
+            };
+            utils::drop_c_string(cstr1);
+            utils::drop_c_string(cstr2);
+
+            j
+        },
+        set_wrap_static_method)
+}
+
 pub(crate) fn set_get_json_method(j: jmethodID) {
     debug("Called set_get_json_method");
     GET_JSON_METHOD.with(|opt| {
@@ -980,6 +1146,142 @@ pub(crate) fn get_get_json_method() -> errors::Result<jmethodID> {
         set_get_json_method)
 }
 
+pub(crate) fn set_is_null_method(j: jmethodID) {
+    debug("Called set_is_null_method");
+    IS_NULL_METHOD.with(|opt| {
+        *opt.borrow_mut() = Some(j);
+    });
+}
+
+pub(crate) fn get_is_null_method() -> errors::Result<jmethodID> {
+    get_cached!(
+        IS_NULL_METHOD,
+        {
+            let env = get_thread_local_env()?;
+
+            let is_null_method_signature = "()Z";
+            let cstr1 = utils::to_c_string("isNull");
+            let cstr2 = utils::to_c_string(is_null_method_signature);
+
+            // Get the method ID for the `NativeInvocation.isNull`
+            let j = unsafe {
+                (opt_to_res(get_jni_get_method_id())?)(
+                    env,
+                    get_native_invocation_class()?,
+                    cstr1,
+                    cstr2,
+                )
+            };
+            utils::drop_c_string(cstr1);
+            utils::drop_c_string(cstr2);
+
+            j
+        },
+        set_is_null_method)
+}
+
+pub(crate) fn set_get_object_method(j: jmethodID) {
+    debug("Called set_get_object_method");
+    GET_OBJECT_METHOD.with(|opt| {
+        *opt.borrow_mut() = Some(j);
+    });
+}
+
+pub(crate) fn get_get_object_method() -> errors::Result<jmethodID> {
+    get_cached!(
+        GET_OBJECT_METHOD,
+        {
+            let env = get_thread_local_env()?;
+
+            let get_object_method_signature = "()Ljava/lang/Object;";
+            let cstr1 = utils::to_c_string("getObject");
+            let cstr2 = utils::to_c_string(get_object_method_signature);
+
+            // Get the method ID for the `NativeInvocation.getObject`
+            let j = unsafe {
+                (opt_to_res(get_jni_get_method_id())?)(
+                    env,
+                    get_native_invocation_class()?,
+                    cstr1,
+                    cstr2,
+                )
+            };
+            utils::drop_c_string(cstr1);
+            utils::drop_c_string(cstr2);
+
+            j
+        },
+        set_get_object_method)
+}
+
+pub(crate) fn set_get_object_class_method(j: jmethodID) {
+    debug("Called set_get_object_class_method");
+    GET_OBJECT_CLASS_METHOD.with(|opt| {
+        *opt.borrow_mut() = Some(j);
+    });
+}
+
+pub(crate) fn get_get_object_class_method() -> errors::Result<jmethodID> {
+    get_cached!(
+        GET_OBJECT_CLASS_METHOD,
+        {
+            let env = get_thread_local_env()?;
+
+            let get_object_class_method_signature = "()Ljava/lang/Class;";
+            let cstr1 = utils::to_c_string("getObjectClass");
+            let cstr2 = utils::to_c_string(get_object_class_method_signature);
+
+            // Get the method ID for the `NativeInvocation.getObjectClass`
+            let j = unsafe {
+                (opt_to_res(get_jni_get_method_id())?)(
+                    env,
+                    get_native_invocation_class()?,
+                    cstr1,
+                    cstr2,
+                )
+            };
+            utils::drop_c_string(cstr1);
+            utils::drop_c_string(cstr2);
+
+            j
+        },
+        set_get_object_class_method)
+}
+
+pub(crate) fn set_class_get_name_method(j: jmethodID) {
+    debug("Called set_class_get_name_method");
+    CLASS_GET_NAME_METHOD.with(|opt| {
+        *opt.borrow_mut() = Some(j);
+    });
+}
+
+pub(crate) fn get_class_get_name_method() -> errors::Result<jmethodID> {
+    get_cached!(
+        CLASS_GET_NAME_METHOD,
+        {
+            let env = get_thread_local_env()?;
+            let class_class = tweaks::find_class(env, "java/lang/Class")?;
+
+            let cstr1 = utils::to_c_string("getName");
+            let cstr2 = utils::to_c_string("()Ljava/lang/String;");
+
+            // Get the method ID for the `Class.getName`
+            let j = unsafe {
+                (opt_to_res(get_jni_get_method_id())?)(
+                    env,
+                    class_class,
+                    cstr1,
+                    cstr2,
+                )
+            };
+            utils::drop_c_string(cstr1);
+            utils::drop_c_string(cstr2);
+
+            j
+        },
+        set_class_get_name_method)
+}
+
 pub(crate) fn set_inv_arg_java_constructor_method(j: jmethodID) {
     debug("Called set_inv_arg_java_constructor_method");
     INV_ARG_JAVA_CONSTRUCTOR_METHOD.with(|opt| {
@@ -0,0 +1,243 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{env, fmt, fs};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::errors;
+use crate::errors::J4RsError;
+use crate::utils;
+
+/// The classpath entries, separated by the platform's classpath separator (`:` or `;`)
+pub(crate) const CLASSPATH_ENV: &str = "J4RS_CLASSPATH";
+/// The Java options, separated by whitespace. Single or double quotes group text that contains whitespace.
+pub(crate) const JAVA_OPTS_ENV: &str = "J4RS_JAVA_OPTS";
+/// The location of the jassets and deps directories
+pub(crate) const BASE_PATH_ENV: &str = "J4RS_BASE_PATH";
+/// The jassets directory, when no base path is defined. Unlike the other variables, it is read without `with_env_config`.
+pub(crate) const JASSETS_PATH_ENV: &str = "J4RS_JASSETS_PATH";
+/// The Maven repositories, as comma separated `id::uri` values
+pub(crate) const MAVEN_REPOS_ENV: &str = "J4RS_MAVEN_REPOS";
+/// A TOML or JSON configuration file
+pub(crate) const CONFIG_FILE_ENV: &str = "J4RS_CONFIG_FILE";
+
+/// JVM settings that come from outside the code: a configuration file or environment variables.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct JvmConfig {
+    pub(crate) classpath: Vec<String>,
+    pub(crate) java_opts: Vec<String>,
+    pub(crate) base_path: Option<String>,
+    pub(crate) maven_repos: Vec<String>,
+}
+
+impl JvmConfig {
+    /// Reads a configuration file. Files with the `json` extension are parsed as JSON, all the others as TOML.
+    pub(crate) fn from_file(path: &Path) -> errors::Result<JvmConfig> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| J4RsError::GeneralError(format!("Could not read the j4rs configuration file {}: {}", path.display(), error)))?;
+        let is_json = path.extension().map(|extension| extension.eq_ignore_ascii_case("json")).unwrap_or(false);
+        let config: JvmConfig = if is_json {
+            serde_json::from_str(&contents)
+                .map_err(|error| J4RsError::ParseError(format!("Invalid j4rs configuration file {}: {}", path.display(), error)))?
+        } else {
+            toml::from_str(&contents)
+                .map_err(|error| J4RsError::ParseError(format!("Invalid j4rs configuration file {}: {}", path.display(), error)))?
+        };
+        check_maven_repos(&config.maven_repos, &format!("the j4rs configuration file {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Reads the configuration from the environment variables, including the file of `J4RS_CONFIG_FILE`.
+    ///
+    /// The values of the environment variables take precedence over the ones of the file.
+    pub(crate) fn from_env() -> errors::Result<JvmConfig> {
+        let file_config = match env::var(CONFIG_FILE_ENV) {
+            Ok(path) if !path.trim().is_empty() => JvmConfig::from_file(Path::new(path.trim()))?,
+            _ => JvmConfig::default(),
+        };
+        Ok(file_config.merge(JvmConfig::from_vars(|name| env::var(name).ok())?))
+    }
+
+    fn from_vars<F>(var: F) -> errors::Result<JvmConfig> where F: Fn(&str) -> Option<String> {
+        let non_empty = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let java_opts = match non_empty(JAVA_OPTS_ENV) {
+            Some(value) => split_java_opts(&value)?,
+            None => Vec::new(),
+        };
+        let maven_repos = non_empty(MAVEN_REPOS_ENV)
+            .map(|value| split_trimmed(&value, ","))
+            .unwrap_or_default();
+        check_maven_repos(&maven_repos, MAVEN_REPOS_ENV)?;
+        Ok(JvmConfig {
+            classpath: non_empty(CLASSPATH_ENV)
+                .map(|value| split_trimmed(&value, utils::classpath_sep()))
+                .unwrap_or_default(),
+            java_opts,
+            base_path: non_empty(BASE_PATH_ENV).map(|value| value.trim().to_string()),
+            maven_repos,
+        })
+    }
+
+    /// Merges another configuration that takes precedence over this one.
+    ///
+    /// The classpath entries and the Java options of `other` are appended, while its Maven repositories are prepended,
+    /// in order to be searched first. Its base path replaces the one of `self`, if it is defined.
+    pub(crate) fn merge(mut self, other: JvmConfig) -> JvmConfig {
+        self.classpath.extend(other.classpath);
+        self.java_opts.extend(other.java_opts);
+        self.base_path = other.base_path.or(self.base_path);
+        self.maven_repos.splice(0..0, other.maven_repos);
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == JvmConfig::default()
+    }
+}
+
+impl fmt::Display for JvmConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "classpath entries [{}], Java options [{}], base path {}, Maven repositories [{}]",
+               self.classpath.join(", "),
+               self.java_opts.join(" "),
+               self.base_path.as_deref().unwrap_or("<default>"),
+               self.maven_repos.join(", "))
+    }
+}
+
+fn split_trimmed(value: &str, separator: &str) -> Vec<String> {
+    value.split(separator)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Splits the Java options at whitespace. Single or double quotes group text that contains whitespace and are removed,
+/// so that `-Dapp.name="My App"` becomes `-Dapp.name=My App`.
+fn split_java_opts(value: &str) -> errors::Result<Vec<String>> {
+    let mut opts = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in value.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.get_or_insert_with(String::new).push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => opts.extend(current.take()),
+            None => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(J4RsError::ParseError(format!("Unterminated quote in {}: {}", JAVA_OPTS_ENV, value)));
+    }
+    opts.extend(current);
+    Ok(opts)
+}
+
+// Every Maven repository must be an `id::uri` value, with a non empty id and uri
+fn check_maven_repos(repos: &[String], source: &str) -> errors::Result<()> {
+    match repos.iter().find(|repo| {
+        !matches!(repo.split_once("::"), Some((id, uri)) if !id.trim().is_empty() && !uri.trim().is_empty())
+    }) {
+        Some(repo) => Err(J4RsError::ParseError(
+            format!("Invalid Maven repository '{}' in {}: expected an id::uri value", repo, source))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod config_unit_tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn config_from_vars() {
+        let mut vars = HashMap::new();
+        vars.insert(CLASSPATH_ENV, format!("a.jar{}b.jar", utils::classpath_sep()));
+        vars.insert(JAVA_OPTS_ENV, " -Xmx512m  -Dkey=value ".to_string());
+        vars.insert(MAVEN_REPOS_ENV, "one::https://one.io, two::https://two.io".to_string());
+        vars.insert(BASE_PATH_ENV, "".to_string());
+        let config = JvmConfig::from_vars(|name| vars.get(name).cloned()).unwrap();
+
+        assert_eq!(config.classpath, vec!["a.jar", "b.jar"]);
+        assert_eq!(config.java_opts, vec!["-Xmx512m", "-Dkey=value"]);
+        assert_eq!(config.maven_repos, vec!["one::https://one.io", "two::https://two.io"]);
+        assert_eq!(config.base_path, None);
+        assert!(JvmConfig::from_vars(|_| None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn quoted_java_opts() {
+        assert_eq!(split_java_opts(r#"-Dapp.name="My App"  '-Dlog.dir=/var/my logs' -Dempty="" -Xmx1g"#).unwrap(),
+                   vec!["-Dapp.name=My App", "-Dlog.dir=/var/my logs", "-Dempty=", "-Xmx1g"]);
+        assert_eq!(split_java_opts(r#"-Dquote='say "hi"'"#).unwrap(), vec![r#"-Dquote=say "hi""#]);
+        assert_eq!(split_java_opts(r#"-Dpath="C:\Program Files\app""#).unwrap(), vec![r"-Dpath=C:\Program Files\app"]);
+        match split_java_opts(r#"-Dapp.name="My App"#) {
+            Err(J4RsError::ParseError(message)) => assert!(message.contains(JAVA_OPTS_ENV)),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn malformed_maven_repos() {
+        for entry in ["https://one.io", "::https://one.io", "one::"] {
+            let value = format!("two::https://two.io,{}", entry);
+            match JvmConfig::from_vars(|name| if name == MAVEN_REPOS_ENV { Some(value.clone()) } else { None }) {
+                Err(J4RsError::ParseError(message)) => {
+                    assert!(message.contains(&format!("'{}'", entry)), "{}", message);
+                    assert!(message.contains(MAVEN_REPOS_ENV), "{}", message);
+                }
+                other => panic!("Unexpected result for {}: {:?}", entry, other),
+            }
+        }
+    }
+
+    #[test]
+    fn config_files() {
+        let dir = env::temp_dir().join("j4rs_config_files");
+        fs::create_dir_all(&dir).unwrap();
+        let toml_path = dir.join("j4rs.toml");
+        fs::write(&toml_path, "classpath = [\"a.jar\"]\njava_opts = [\"-Xmx1g\"]\nbase_path = \"/opt/app\"\n").unwrap();
+        let json_path = dir.join("j4rs.json");
+        fs::write(&json_path, r#"{"java_opts": ["-Xmx2g"], "maven_repos": ["one::https://one.io"]}"#).unwrap();
+        let invalid_path = dir.join("invalid.toml");
+        fs::write(&invalid_path, "classpth = [\"a.jar\"]").unwrap();
+        let invalid_repo_path = dir.join("invalid_repo.toml");
+        fs::write(&invalid_repo_path, "maven_repos = [\"https://one.io\"]").unwrap();
+
+        let from_toml = JvmConfig::from_file(&toml_path).unwrap();
+        assert_eq!(from_toml.classpath, vec!["a.jar"]);
+        assert_eq!(from_toml.base_path.as_deref(), Some("/opt/app"));
+
+        let merged = from_toml.merge(JvmConfig::from_file(&json_path).unwrap());
+        assert_eq!(merged.java_opts, vec!["-Xmx1g", "-Xmx2g"]);
+        assert_eq!(merged.base_path.as_deref(), Some("/opt/app"));
+        assert_eq!(merged.maven_repos, vec!["one::https://one.io"]);
+        let merged = merged.merge(JvmConfig { maven_repos: vec!["two::https://two.io".to_string()], ..JvmConfig::default() });
+        assert_eq!(merged.maven_repos, vec!["two::https://two.io", "one::https://one.io"]);
+
+        assert!(JvmConfig::from_file(&invalid_path).is_err());
+        assert!(matches!(JvmConfig::from_file(&invalid_repo_path), Err(J4RsError::ParseError(message)) if message.contains("'https://one.io'")));
+        assert!(JvmConfig::from_file(&dir.join("missing.toml")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Accounting of the Java references that are held by j4rs.
+//!
+//! The tracking is opt-in. While it is enabled, j4rs records every `Instance`, global reference and callback channel
+//! that is created, along with the name of the Java class and the call site that created it.
+//! The records are removed when the `Instance`s, references and channels are released.
+//!
+//! The global references that j4rs caches for the lifetime of a thread (classes and the like) are not reported.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::panic::Location;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread::{self, ThreadId};
+
+use jni_sys::{JNI_TRUE, JNIEnv, jobject};
+
+use crate::{cache, jni_utils};
+use crate::logger::error;
+
+const UNKNOWN_CLASS: &str = "<unknown>";
+
+// Whether the tracking is enabled for the whole process
+static TRACKING_ENABLED: AtomicBool = AtomicBool::new(false);
+// The number of the running `leaks_of` blocks. The tracking is enabled while there is at least one.
+static TRACKING_SCOPES: AtomicUsize = AtomicUsize::new(0);
+// Whether the tracking was ever enabled. If not, there is nothing to untrack.
+static TRACKING_USED: AtomicBool = AtomicBool::new(false);
+// Whether the report is dumped when the process exits
+static DUMP_AT_EXIT_REGISTERED: AtomicBool = AtomicBool::new(false);
+// Orders the records, so that the ones created during a `leaks_of` block can be identified
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    // The live references, by their kind and address.
+    static ref LIVE_REFERENCES: Mutex<HashMap<(Kind, usize), Record>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Kind {
+    Instance,
+    GlobalRef,
+    CallbackChannel,
+}
+
+struct Record {
+    class_name: String,
+    call_site: &'static Location<'static>,
+    thread: ThreadId,
+    sequence: u64,
+}
+
+/// Enables the tracking of the `Instance`s, global references and callback channels for the whole process.
+pub fn enable_tracking() {
+    TRACKING_USED.store(true, Ordering::SeqCst);
+    TRACKING_ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Disables the tracking. The already tracked items are still removed from the report when they are released.
+pub fn disable_tracking() {
+    TRACKING_ENABLED.store(false, Ordering::SeqCst);
+}
+
+/// Returns true if the created `Instance`s, global references and callback channels are tracked.
+pub fn is_tracking_enabled() -> bool {
+    TRACKING_ENABLED.load(Ordering::SeqCst) || TRACKING_SCOPES.load(Ordering::SeqCst) > 0
+}
+
+/// Returns a report of all the tracked items that are still alive.
+pub fn report() -> LeakReport {
+    collect_report(|_| true)
+}
+
+/// Prints the report of the tracked items that are still alive to the standard error, when the process exits.
+///
+/// It also enables the tracking.
+pub fn dump_report_at_exit() {
+    enable_tracking();
+    if !DUMP_AT_EXIT_REGISTERED.swap(true, Ordering::SeqCst) && unsafe { libc::atexit(dump_report) } != 0 {
+        DUMP_AT_EXIT_REGISTERED.store(false, Ordering::SeqCst);
+        error("Could not register the dump of the j4rs references report at exit");
+    }
+}
+
+extern "C" fn dump_report() {
+    eprintln!("{}", report());
+}
+
+/// Executes `f`, tracking the items that are created by the current thread while it runs.
+///
+/// Returns the result of `f`, along with a report of the tracked items that were not released when `f` returned.
+pub fn leaks_of<T, F>(f: F) -> (T, LeakReport) where F: FnOnce() -> T {
+    let scope = TrackingScope::start();
+    let result = f();
+    let report = collect_report(|record| record.thread == scope.thread && record.sequence >= scope.first_sequence);
+    (result, report)
+}
+
+/// Executes `f` and panics if any of the `Instance`s, global references or callback channels
+/// that were created by the current thread while `f` was running was not released.
+///
+/// Meant to be used by tests.
+pub fn assert_no_leaks<T, F>(f: F) -> T where F: FnOnce() -> T {
+    let (result, report) = leaks_of(f);
+    if !report.is_empty() {
+        panic!("Leaked Java references found. {}", report);
+    }
+    result
+}
+
+// Enables the tracking while a `leaks_of` block runs
+struct TrackingScope {
+    thread: ThreadId,
+    first_sequence: u64,
+}
+
+impl TrackingScope {
+    fn start() -> TrackingScope {
+        TRACKING_USED.store(true, Ordering::SeqCst);
+        TRACKING_SCOPES.fetch_add(1, Ordering::SeqCst);
+        TrackingScope {
+            thread: thread::current().id(),
+            first_sequence: NEXT_SEQUENCE.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl Drop for TrackingScope {
+    fn drop(&mut self) {
+        TRACKING_SCOPES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The live items that are tracked, grouped by the Java class and the call site that created them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeakReport {
+    instances: Vec<LiveGroup>,
+    global_refs: Vec<LiveGroup>,
+    callback_channels: Vec<LiveGroup>,
+}
+
+impl LeakReport {
+    /// The live `Instance`s.
+    pub fn instances(&self) -> &[LiveGroup] {
+        &self.instances
+    }
+
+    /// The live global references, including the ones held by the live `Instance`s.
+    pub fn global_refs(&self) -> &[LiveGroup] {
+        &self.global_refs
+    }
+
+    /// The live `InstanceReceiver`s of callback channels.
+    pub fn callback_channels(&self) -> &[LiveGroup] {
+        &self.callback_channels
+    }
+
+    /// The number of the live `Instance`s.
+    pub fn live_instances(&self) -> usize {
+        self.instances.iter().map(|group| group.count).sum()
+    }
+
+    /// The number of the live global references.
+    pub fn live_global_refs(&self) -> usize {
+        self.global_refs.iter().map(|group| group.count).sum()
+    }
+
+    /// The number of the live callback channels.
+    pub fn live_callback_channels(&self) -> usize {
+        self.callback_channels.iter().map(|group| group.count).sum()
+    }
+
+    /// Returns true if there are no live items.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty() && self.global_refs.is_empty() && self.callback_channels.is_empty()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "j4rs live references: {} Instances, {} global references, {} callback channels",
+               self.live_instances(), self.live_global_refs(), self.live_callback_channels())?;
+        for (title, groups) in [("Instances", &self.instances), ("Global references", &self.global_refs), ("Callback channels", &self.callback_channels)] {
+            if !groups.is_empty() {
+                write!(f, "\n{}:", title)?;
+                for group in groups {
+                    write!(f, "\n    {}", group)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Live items of the same Java class that were created at the same call site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveGroup {
+    class_name: String,
+    call_site: String,
+    count: usize,
+}
+
+impl LiveGroup {
+    /// The name of the Java class.
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The location of the code that created the items, as `file:line:column`.
+    pub fn call_site(&self) -> &str {
+        &self.call_site
+    }
+
+    /// The number of the live items.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl fmt::Display for LiveGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} x {} created at {}", self.count, self.class_name, self.call_site)
+    }
+}
+
+fn collect_report<P>(predicate: P) -> LeakReport where P: Fn(&Record) -> bool {
+    let mut grouped: BTreeMap<(Kind, String, String), usize> = BTreeMap::new();
+    match LIVE_REFERENCES.lock() {
+        Ok(live) => {
+            for ((kind, _), record) in live.iter().filter(|(_, record)| predicate(record)) {
+                *grouped.entry((*kind, record.class_name.clone(), record.call_site.to_string())).or_insert(0) += 1;
+            }
+        }
+        Err(_) => error("Could not get the lock for the tracked j4rs references"),
+    }
+    let mut report = LeakReport::default();
+    for ((kind, class_name, call_site), count) in grouped {
+        let groups = match kind {
+            Kind::Instance => &mut report.instances,
+            Kind::GlobalRef => &mut report.global_refs,
+            Kind::CallbackChannel => &mut report.callback_channels,
+        };
+        groups.push(LiveGroup { class_name, call_site, count });
+    }
+    for groups in [&mut report.instances, &mut report.global_refs, &mut report.callback_channels] {
+        // The biggest groups first
+        groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+    }
+    report
+}
+
+#[track_caller]
+fn track(kind: Kind, address: usize, class_name: String) {
+    let record = Record {
+        class_name,
+        call_site: Location::caller(),
+        thread: thread::current().id(),
+        sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::SeqCst),
+    };
+    match LIVE_REFERENCES.lock() {
+        Ok(mut live) => {
+            live.insert((kind, address), record);
+        }
+        Err(_) => error("Could not get the lock for the tracked j4rs references"),
+    }
+}
+
+fn untrack(kind: Kind, address: usize) {
+    if !TRACKING_USED.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(mut live) = LIVE_REFERENCES.lock() {
+        live.remove(&(kind, address));
+    }
+}
+
+#[track_caller]
+pub(crate) fn track_instance(jinstance: jobject, class_name: &str) {
+    if !is_tracking_enabled() || jinstance.is_null() {
+        return;
+    }
+    let class_name = if class_name == cache::UNKNOWN_FOR_RUST {
+        with_env(|jni_env| unsafe { wrapped_class_name(jni_env, jinstance) })
+    } else {
+        class_name.to_string()
+    };
+    track(Kind::Instance, jinstance as usize, class_name);
+}
+
+pub(crate) fn untrack_instance(jinstance: jobject) {
+    untrack(Kind::Instance, jinstance as usize);
+}
+
+#[track_caller]
+pub(crate) fn track_global_ref(global: jobject) {
+    if !is_tracking_enabled() || global.is_null() {
+        return;
+    }
+    let class_name = with_env(|jni_env| unsafe { object_class_name(jni_env, global) });
+    track(Kind::GlobalRef, global as usize, class_name);
+}
+
+pub(crate) fn untrack_global_ref(global: jobject) {
+    untrack(Kind::GlobalRef, global as usize);
+}
+
+#[track_caller]
+pub(crate) fn track_callback_channel(tx_address: i64, class_name: &str) {
+    if is_tracking_enabled() {
+        track(Kind::CallbackChannel, tx_address as usize, class_name.to_string());
+    }
+}
+
+pub(crate) fn untrack_callback_channel(tx_address: i64) {
+    untrack(Kind::CallbackChannel, tx_address as usize);
+}
+
+// Resolves a class name using the JNIEnv of the current thread, if there is one
+fn with_env<F>(f: F) -> String where F: FnOnce(*mut JNIEnv) -> Option<String> {
+    cache::get_thread_local_env_opt()
+        .and_then(f)
+        .unwrap_or_else(|| UNKNOWN_CLASS.to_string())
+}
+
+// The name of the class of a Java object
+unsafe fn object_class_name(jni_env: *mut JNIEnv, object: jobject) -> Option<String> {
+    if exception_pending(jni_env) {
+        return None;
+    }
+    let class = ((**jni_env).GetObjectClass?)(jni_env, object);
+    class_name(jni_env, class)
+}
+
+// The name of the class that a NativeInvocation treats its object as
+unsafe fn wrapped_class_name(jni_env: *mut JNIEnv, jinstance: jobject) -> Option<String> {
+    if exception_pending(jni_env) {
+        return None;
+    }
+    let class = (cache::get_jni_call_object_method()?)(jni_env, jinstance, cache::get_get_object_class_method().ok()?);
+    class_name(jni_env, class)
+}
+
+// Calls `Class.getName` on a local reference of a class and deletes the reference
+unsafe fn class_name(jni_env: *mut JNIEnv, class: jobject) -> Option<String> {
+    if exception_pending(jni_env) || class.is_null() {
+        clear_exception(jni_env);
+        return None;
+    }
+    let name = cache::get_class_get_name_method().ok().and_then(|get_name| {
+        let name = (cache::get_jni_call_object_method()?)(jni_env, class, get_name);
+        if exception_pending(jni_env) || name.is_null() {
+            clear_exception(jni_env);
+            return None;
+        }
+        let rust_name = jni_utils::java_string_to_rust_string(jni_env, name).ok();
+        jni_utils::delete_java_local_ref(jni_env, name);
+        rust_name
+    });
+    jni_utils::delete_java_local_ref(jni_env, class);
+    name
+}
+
+unsafe fn exception_pending(jni_env: *mut JNIEnv) -> bool {
+    match (**jni_env).ExceptionCheck {
+        Some(exc) => exc(jni_env) == JNI_TRUE,
+        None => true,
+    }
+}
+
+unsafe fn clear_exception(jni_env: *mut JNIEnv) {
+    if let Some(exclear) = (**jni_env).ExceptionClear {
+        exclear(jni_env);
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_unit_tests {
+    use super::*;
+
+    fn group(class_name: &str, count: usize) -> LiveGroup {
+        LiveGroup { class_name: class_name.to_string(), call_site: "src/a.rs:1:1".to_string(), count }
+    }
+
+    #[test]
+    fn report_display() {
+        let report = LeakReport {
+            instances: vec![group("java.lang.String", 2)],
+            global_refs: vec![group("java.lang.String", 2), group("java.lang.Integer", 1)],
+            callback_channels: Vec::new(),
+        };
+        assert_eq!(report.live_instances(), 2);
+        assert_eq!(report.live_global_refs(), 3);
+        assert!(!report.is_empty());
+        assert_eq!(format!("{}", report),
+                   "j4rs live references: 2 Instances, 3 global references, 0 callback channels\n\
+                   Instances:\n    2 x java.lang.String created at src/a.rs:1:1\n\
+                   Global references:\n    2 x java.lang.String created at src/a.rs:1:1\n    1 x java.lang.Integer created at src/a.rs:1:1");
+        assert!(LeakReport::default().is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{env, fs, process};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sha2::{Digest, Sha256};
+
+use crate::errors;
+use crate::logger::info;
+
+// Defines EMBEDDED_JAR, EMBEDDED_JAR_NAME and EMBEDDED_JAR_SHA256
+include!(concat!(env!("OUT_DIR"), "/j4rs_embedded.rs"));
+
+// Makes the names of the partially extracted files unique within the process
+static EXTRACTIONS: AtomicUsize = AtomicUsize::new(0);
+
+// Returns the jassets directory that contains the embedded j4rs jar, extracting the jar if needed.
+//
+// The directory is keyed by the hash of the jar, so that binaries with different j4rs jars do not interfere.
+pub(crate) fn jassets_path() -> errors::Result<PathBuf> {
+    let jassets_path = cache_dir().join("j4rs").join(&EMBEDDED_JAR_SHA256[..16]).join("jassets");
+    extract(EMBEDDED_JAR, EMBEDDED_JAR_SHA256, &jassets_path, EMBEDDED_JAR_NAME)?;
+    Ok(jassets_path)
+}
+
+// Writes the contents to a file of the directory, unless the file already exists with the given SHA-256 hash.
+fn extract(contents: &[u8], sha256: &str, dir: &Path, file_name: &str) -> errors::Result<PathBuf> {
+    let target = dir.join(file_name);
+    let extracted = fs::read(&target)
+        .map(|existing| format!("{:x}", Sha256::digest(&existing)) == sha256)
+        .unwrap_or(false);
+    if !extracted {
+        info(&format!("Extracting the embedded {} to {}", file_name, dir.display()));
+        fs::create_dir_all(dir)?;
+        // Other processes and threads may be extracting the same file, so each one writes its own partial file.
+        // The rename makes the complete file appear at once.
+        let partial = dir.join(format!("{}.{}.{}.partial", file_name, process::id(), EXTRACTIONS.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&partial, contents)?;
+        if let Err(error) = fs::rename(&partial, &target) {
+            let _ = fs::remove_file(&partial);
+            return Err(error.into());
+        }
+    }
+    Ok(target)
+}
+
+// The cache directory of the user, or the temporary directory if it is not defined
+fn cache_dir() -> PathBuf {
+    let user_cache_dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+    user_cache_dir.unwrap_or_else(env::temp_dir)
+}
+
+#[cfg(test)]
+mod embedded_unit_tests {
+    use super::*;
+
+    #[test]
+    fn extract_embedded_jar() {
+        let dir = env::temp_dir().join("j4rs_extract_embedded_jar");
+        let _ = fs::remove_dir_all(&dir);
+        let sha256 = format!("{:x}", Sha256::digest(b"contents"));
+
+        let extracted = extract(b"contents", &sha256, &dir, "some.jar").unwrap();
+        assert_eq!(fs::read(&extracted).unwrap(), b"contents");
+        // Already extracted
+        assert_eq!(extract(b"contents", &sha256, &dir, "some.jar").unwrap(), extracted);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        // A file with other contents of the same length is replaced
+        fs::write(&extracted, b"CONTENTS").unwrap();
+        extract(b"contents", &sha256, &dir, "some.jar").unwrap();
+        assert_eq!(fs::read(&extracted).unwrap(), b"contents");
+        let _ = fs::remove_dir_all(&dir);
+
+        let jassets_path = jassets_path().unwrap();
+        assert!(jassets_path.ends_with(Path::new(&EMBEDDED_JAR_SHA256[..16]).join("jassets")));
+        assert_eq!(fs::read(jassets_path.join(EMBEDDED_JAR_NAME)).unwrap(), EMBEDDED_JAR);
+    }
+
+    #[test]
+    fn extract_concurrently() {
+        let dir = env::temp_dir().join("j4rs_extract_concurrently");
+        let _ = fs::remove_dir_all(&dir);
+        let contents = vec![7u8; 1 << 20];
+        let sha256 = format!("{:x}", Sha256::digest(&contents));
+
+        let handles: Vec<_> = (0..8).map(|_| {
+            let (contents, sha256, dir) = (contents.clone(), sha256.clone(), dir.clone());
+            std::thread::spawn(move || extract(&contents, &sha256, &dir, "some.jar").unwrap())
+        }).collect();
+        for handle in handles {
+            assert_eq!(fs::read(handle.join().unwrap()).unwrap(), contents);
+        }
+        // No partial files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
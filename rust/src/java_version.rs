@@ -0,0 +1,166 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use jni_sys::{jint, JNI_VERSION_1_8};
+use serde::Serialize;
+
+use crate::api::_JNI_VERSION_10;
+use crate::errors;
+use crate::errors::J4RsError;
+
+const JNI_VERSION_9: jint = 0x00090000;
+const JNI_VERSION_19: jint = 0x00130000;
+const JNI_VERSION_20: jint = 0x00140000;
+const JNI_VERSION_21: jint = 0x00150000;
+
+/// The version of the Java runtime of a JVM.
+///
+/// The numbering follows the one of `java.lang.Runtime.Version`: Java 8 is feature 8, no matter that its
+/// `java.version` is `1.8.0_<update>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaVersion {
+    feature: u32,
+    interim: u32,
+    update: u32,
+    vendor: String,
+}
+
+impl JavaVersion {
+    /// Parses the values of the `java.version` and `java.vendor` system properties.
+    pub(crate) fn parse(version: &str, vendor: &str) -> errors::Result<JavaVersion> {
+        let invalid = || J4RsError::ParseError(format!("Invalid Java version {}", version));
+        // Pre-release and build information, like in `17-ea` or `11.0.2+9`
+        let numbers = version.split(['-', '+']).next().unwrap_or_default();
+        let parse_number = |number: &str| number.parse::<u32>().map_err(|_| invalid());
+        let (feature, interim, update) = match numbers.strip_prefix("1.") {
+            // Legacy versions, like `1.8.0_362`
+            Some(legacy) => {
+                let (numbers, update) = match legacy.split_once('_') {
+                    Some((numbers, update)) => (numbers, parse_number(update)?),
+                    None => (legacy, 0),
+                };
+                let feature = parse_number(numbers.split('.').next().unwrap_or_default())?;
+                (feature, 0, update)
+            }
+            None => {
+                let mut parts = numbers.split('.');
+                let feature = parse_number(parts.next().unwrap_or_default())?;
+                let interim = parts.next().map(parse_number).transpose()?.unwrap_or(0);
+                let update = parts.next().map(parse_number).transpose()?.unwrap_or(0);
+                (feature, interim, update)
+            }
+        };
+        Ok(JavaVersion { feature, interim, update, vendor: vendor.to_string() })
+    }
+
+    /// The feature release number, like 8, 11 or 17.
+    pub fn feature(&self) -> u32 {
+        self.feature
+    }
+
+    /// The interim release number. Always 0 for the versions up to Java 9.
+    pub fn interim(&self) -> u32 {
+        self.interim
+    }
+
+    /// The update release number.
+    pub fn update(&self) -> u32 {
+        self.update
+    }
+
+    /// The vendor of the Java runtime, as in the `java.vendor` system property.
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+}
+
+impl fmt::Display for JavaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{} ({})", self.feature, self.interim, self.update, self.vendor)
+    }
+}
+
+/// The JNI version that is requested when creating a JVM.
+///
+/// Creating the JVM fails if the Java runtime does not support the requested version.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum JniVersion {
+    /// Supported by Java 8 and newer
+    #[default]
+    V1_8,
+    /// Supported by Java 9 and newer
+    V9,
+    /// Supported by Java 10 and newer
+    V10,
+    /// Supported by Java 19 and newer
+    V19,
+    /// Supported by Java 20 and newer
+    V20,
+    /// Supported by Java 21 and newer
+    V21,
+}
+
+impl JniVersion {
+    pub(crate) fn as_jint(&self) -> jint {
+        match self {
+            JniVersion::V1_8 => JNI_VERSION_1_8,
+            JniVersion::V9 => JNI_VERSION_9,
+            JniVersion::V10 => _JNI_VERSION_10,
+            JniVersion::V19 => JNI_VERSION_19,
+            JniVersion::V20 => JNI_VERSION_20,
+            JniVersion::V21 => JNI_VERSION_21,
+        }
+    }
+}
+
+impl fmt::Display for JniVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = match self {
+            JniVersion::V1_8 => "1.8",
+            JniVersion::V9 => "9",
+            JniVersion::V10 => "10",
+            JniVersion::V19 => "19",
+            JniVersion::V20 => "20",
+            JniVersion::V21 => "21",
+        };
+        write!(f, "{}", version)
+    }
+}
+
+#[cfg(test)]
+mod java_version_unit_tests {
+    use super::*;
+
+    #[test]
+    fn parse_java_versions() {
+        let check = |version: &str, expected: (u32, u32, u32)| {
+            let parsed = JavaVersion::parse(version, "A vendor").unwrap();
+            assert_eq!((parsed.feature(), parsed.interim(), parsed.update()), expected, "{}", version);
+        };
+        check("1.8.0_362", (8, 0, 362));
+        check("1.8.0", (8, 0, 0));
+        check("9", (9, 0, 0));
+        check("11.0.20", (11, 0, 20));
+        check("17.0.8.1", (17, 0, 8));
+        check("21-ea", (21, 0, 0));
+        check("11.0.2+9", (11, 0, 2));
+
+        assert!(JavaVersion::parse("", "A vendor").is_err());
+        assert!(JavaVersion::parse("eleven", "A vendor").is_err());
+        assert_eq!(JavaVersion::parse("17.0.8", "A vendor").unwrap().to_string(), "17.0.8 (A vendor)");
+        assert_eq!(JniVersion::default().as_jint(), JNI_VERSION_1_8);
+    }
+}
@@ -12,18 +12,37 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::os::raw::c_char;
-use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
-use jni_sys::{jint, JNI_TRUE, JNIEnv, jobject, jobjectRefType, jstring};
+use jni_sys::{jchar, jint, JNI_TRUE, JNIEnv, jobject, jobjectRefType, jsize, jstring};
 
 use crate::{InvocationArg, Jvm};
 use crate::cache;
+use crate::diagnostics;
 use crate::errors;
 use crate::errors::opt_to_res;
 use crate::logger::{debug, error};
 use crate::utils;
 
+lazy_static! {
+    // The global references that were dropped by threads that could not release them because they had no JNIEnv.
+    static ref DEFERRED_RELEASES: Mutex<Vec<DeferredRef>> = Mutex::new(Vec::new());
+}
+
+// The number of the references that wait in DEFERRED_RELEASES
+static PENDING_DEFERRED_RELEASES: AtomicUsize = AtomicUsize::new(0);
+// The number of all the references that were ever deferred
+static TOTAL_DEFERRED_RELEASES: AtomicUsize = AtomicUsize::new(0);
+
+// A reference waiting to be released. Global and weak global references can be deleted by any thread.
+enum DeferredRef {
+    Global(jobject),
+    WeakGlobal(jobject),
+}
+
+unsafe impl Send for DeferredRef {}
+
 pub(crate) fn invocation_arg_jobject_from_rust_serialized(ia: &InvocationArg, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
     unsafe {
         let (class_name, json) = match ia {
@@ -124,6 +143,7 @@ pub(crate) fn invocation_arg_jobject_from_java(ia: &InvocationArg, jni_env: *mut
     }
 }
 
+#[track_caller]
 pub(crate) fn create_global_ref_from_local_ref(local_ref: jobject, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
     unsafe {
         match ((**jni_env).NewGlobalRef,
@@ -147,6 +167,7 @@ pub(crate) fn create_global_ref_from_local_ref(local_ref: jobject, jni_env: *mut
                     (exclear)(jni_env);
                     Err(errors::J4RsError::JavaError("An Exception was thrown by Java while creating global ref... Please check the logs or the console.".to_string()))
                 } else {
+                    diagnostics::track_global_ref(global);
                     Ok(global)
                 }
             }
@@ -157,7 +178,8 @@ pub(crate) fn create_global_ref_from_local_ref(local_ref: jobject, jni_env: *mut
     }
 }
 
-pub(crate) fn _create_weak_global_ref_from_global_ref(global_ref: jobject, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
+#[track_caller]
+pub(crate) fn create_weak_global_ref_from_global_ref(global_ref: jobject, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
     unsafe {
         match ((**jni_env).NewWeakGlobalRef,
                (**jni_env).ExceptionCheck,
@@ -175,6 +197,7 @@ pub(crate) fn _create_weak_global_ref_from_global_ref(global_ref: jobject, jni_e
                     (exclear)(jni_env);
                     Err(errors::J4RsError::JavaError("An Exception was thrown by Java while creating a weak global ref... Please check the logs or the console.".to_string()))
                 } else {
+                    diagnostics::track_global_ref(global);
                     Ok(global)
                 }
             }
@@ -187,6 +210,11 @@ pub(crate) fn _create_weak_global_ref_from_global_ref(global_ref: jobject, jni_e
 
 /// Deletes the java ref from the memory
 pub(crate) fn delete_java_ref(jni_env: *mut JNIEnv, jinstance: jobject) {
+    diagnostics::untrack_global_ref(jinstance);
+    // The references are gone along with the destroyed JVM
+    if cache::is_jvm_destroyed() {
+        return;
+    }
     unsafe {
         match ((**jni_env).DeleteGlobalRef,
                (**jni_env).ExceptionCheck,
@@ -210,8 +238,97 @@ pub(crate) fn delete_java_ref(jni_env: *mut JNIEnv, jinstance: jobject) {
     }
 }
 
+/// Deletes the weak java ref from the memory
+pub(crate) fn delete_java_weak_ref(jni_env: *mut JNIEnv, jinstance: jobject) {
+    diagnostics::untrack_global_ref(jinstance);
+    // The references are gone along with the destroyed JVM
+    if cache::is_jvm_destroyed() {
+        return;
+    }
+    unsafe {
+        match ((**jni_env).DeleteWeakGlobalRef,
+               (**jni_env).ExceptionCheck,
+               (**jni_env).ExceptionDescribe,
+               (**jni_env).ExceptionClear) {
+            (Some(dwgr), Some(exc), Some(exd), Some(exclear)) => {
+                dwgr(
+                    jni_env,
+                    jinstance,
+                );
+                if (exc)(jni_env) == JNI_TRUE {
+                    (exd)(jni_env);
+                    (exclear)(jni_env);
+                    error("An Exception was thrown by Java... Please check the logs or the console.");
+                }
+            }
+            (_, _, _, _) => {
+                error("Could retrieve the native functions to drop the weak Java ref. This may lead to memory leaks");
+            }
+        }
+    }
+}
+
+/// Queues a global java ref to be deleted later by a thread that is attached to the JVM
+pub(crate) fn defer_java_ref_release(jinstance: jobject) {
+    defer_release(DeferredRef::Global(jinstance));
+}
+
+/// Queues a weak global java ref to be deleted later by a thread that is attached to the JVM
+pub(crate) fn defer_java_weak_ref_release(jinstance: jobject) {
+    defer_release(DeferredRef::WeakGlobal(jinstance));
+}
+
+fn defer_release(deferred_ref: DeferredRef) {
+    match DEFERRED_RELEASES.lock() {
+        Ok(mut deferred) => {
+            deferred.push(deferred_ref);
+            PENDING_DEFERRED_RELEASES.fetch_add(1, Ordering::SeqCst);
+            TOTAL_DEFERRED_RELEASES.fetch_add(1, Ordering::SeqCst);
+        }
+        Err(_) => error("Could not defer the release of a Java ref. This may lead to memory leaks"),
+    }
+}
+
+/// Deletes the global java refs that were queued by `defer_java_ref_release`
+pub(crate) fn release_deferred_java_refs(jni_env: *mut JNIEnv) {
+    if PENDING_DEFERRED_RELEASES.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    let to_release: Vec<DeferredRef> = match DEFERRED_RELEASES.lock() {
+        Ok(mut deferred) => {
+            PENDING_DEFERRED_RELEASES.fetch_sub(deferred.len(), Ordering::SeqCst);
+            deferred.drain(..).collect()
+        }
+        Err(_) => {
+            error("Could not release the deferred Java refs. This may lead to memory leaks");
+            return;
+        }
+    };
+    debug(&format!("Releasing {} deferred Java refs", to_release.len()));
+    for deferred_ref in to_release {
+        match deferred_ref {
+            DeferredRef::Global(jinstance) => delete_java_ref(jni_env, jinstance),
+            DeferredRef::WeakGlobal(jinstance) => delete_java_weak_ref(jni_env, jinstance),
+        }
+    }
+}
+
+/// The number of the java refs that are queued by `defer_java_ref_release` and not released yet
+pub(crate) fn pending_deferred_java_ref_releases() -> usize {
+    PENDING_DEFERRED_RELEASES.load(Ordering::SeqCst)
+}
+
+/// The number of the global java refs that have been queued by `defer_java_ref_release` since the process started
+pub(crate) fn deferred_java_ref_releases() -> usize {
+    TOTAL_DEFERRED_RELEASES.load(Ordering::SeqCst)
+}
+
 /// Deletes the java ref from the memory
 pub(crate) fn delete_java_local_ref(jni_env: *mut JNIEnv, jinstance: jobject) {
+    // The references are gone along with the destroyed JVM
+    if cache::is_jvm_destroyed() {
+        return;
+    }
     unsafe {
         match ((**jni_env).DeleteLocalRef,
                (**jni_env).ExceptionCheck,
@@ -237,11 +354,14 @@ pub(crate) fn delete_java_local_ref(jni_env: *mut JNIEnv, jinstance: jobject) {
 
 pub(crate) fn global_jobject_from_str(string: &str, jni_env: *mut JNIEnv) -> errors::Result<jobject> {
     unsafe {
-        let tmp = utils::to_c_string_struct(string);
-        let obj = (opt_to_res(cache::get_jni_new_string_utf())?)(
+        // Java Strings are UTF-16. Using the UTF-16 representation, all the Unicode characters and the NULs are transferred unaltered.
+        let utf16 = utils::to_utf16(string);
+        let obj = (opt_to_res(cache::get_jni_new_string())?)(
             jni_env,
-            tmp.as_ptr(),
+            utf16.as_ptr(),
+            utf16.len() as jsize,
         );
+        let obj = Jvm::do_return(jni_env, obj)?;
         let gr = create_global_ref_from_local_ref(obj, jni_env)?;
         Ok(gr)
     }
@@ -300,18 +420,27 @@ pub(crate) fn global_jobject_from_i64(a: &i64, jni_env: *mut JNIEnv) -> errors::
 }
 
 pub fn jstring_to_rust_string(jvm: &Jvm, java_string: jstring) -> errors::Result<String> {
+    java_string_to_rust_string(jvm.jni_env, java_string)
+}
+
+pub(crate) fn java_string_to_rust_string(jni_env: *mut JNIEnv, java_string: jstring) -> errors::Result<String> {
+    if java_string.is_null() {
+        return Err(errors::J4RsError::RustError("Cannot create a Rust String from a null Java String".to_string()));
+    }
     unsafe {
-        let s = (opt_to_res(cache::get_jni_get_string_utf_chars())?)(
-            jvm.jni_env,
+        let len = (opt_to_res(cache::get_jni_get_string_length())?)(
+            jni_env,
             java_string,
-            ptr::null_mut(),
-        ) as *mut c_char;
-        let rust_string = utils::to_rust_string(s);
-        (opt_to_res(cache::get_jni_release_string_utf_chars())?)(
-            jvm.jni_env,
+        );
+        let mut utf16: Vec<jchar> = vec![0; len as usize];
+        (opt_to_res(cache::get_jni_get_string_region())?)(
+            jni_env,
             java_string,
-            s,
+            0,
+            len,
+            utf16.as_mut_ptr(),
         );
-        Jvm::do_return(jvm.jni_env, rust_string)
+        Jvm::do_return(jni_env, ())?;
+        utils::from_utf16(&utf16)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,269 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use jni_sys::{JavaVM, JavaVMAttachArgs, JNI_EDETACHED, JNI_OK, JNI_VERSION_1_8, JNIEnv, jobject};
+use serde::Serialize;
+
+use crate::{cache, errors, Instance, jni_utils, Jvm, JvmBuilder, utils};
+use crate::errors::{J4RsError, opt_to_res};
+use crate::logger::{debug, error, warn};
+
+lazy_static! {
+    // The handle of the default JVM of the process.
+    static ref DEFAULT_JVM_HANDLE: Mutex<Option<JvmHandle>> = Mutex::new(None);
+}
+
+// Whether the reference reaper thread is started
+static REFERENCE_REAPER_STARTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // Detaches the current thread when the thread exits, if the attachment was done via a JvmHandle.
+    static THREAD_ATTACHMENT: RefCell<Option<ThreadAttachment>> = RefCell::new(None);
+}
+
+/// Defines how the threads are attached to the Java VM.
+///
+/// By default, the threads are attached as non-daemon threads, with names and groups assigned by Java.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AttachOptions {
+    daemon: bool,
+    thread_name: Option<String>,
+    // A reference to a Java object, that has no meaningful serialized form
+    #[serde(skip)]
+    thread_group: Option<Arc<ThreadGroupRef>>,
+}
+
+impl AttachOptions {
+    pub fn new() -> AttachOptions {
+        AttachOptions::default()
+    }
+
+    /// If true, the threads are attached as daemon threads and do not prevent the Java VM from shutting down.
+    pub fn daemon(mut self, daemon: bool) -> AttachOptions {
+        self.daemon = daemon;
+        self
+    }
+
+    /// The name of the Java thread that corresponds to the attached thread.
+    pub fn thread_name(mut self, thread_name: &str) -> AttachOptions {
+        self.thread_name = Some(thread_name.to_string());
+        self
+    }
+
+    /// The `java.lang.ThreadGroup` that the attached threads are added to.
+    ///
+    /// The thread group is kept alive until these `AttachOptions` and all their clones are dropped.
+    pub fn thread_group(mut self, jvm: &Jvm, thread_group: &Instance) -> errors::Result<AttachOptions> {
+        let group = thread_group.wrapped_object_global_ref(jvm.jni_env)?;
+        self.thread_group = Some(Arc::new(ThreadGroupRef(group)));
+        Ok(self)
+    }
+
+    // Attaches the current thread to the Java VM and returns its JNIEnv.
+    pub(crate) fn attach(&self, java_vm: *mut JavaVM) -> errors::Result<*mut JNIEnv> {
+        let thread_name = self.thread_name.as_ref().map(|name| utils::to_modified_utf8(name));
+        let mut attach_args = JavaVMAttachArgs {
+            version: JNI_VERSION_1_8,
+            name: thread_name.as_ref().map(|name| name.as_ptr() as *mut c_char).unwrap_or(ptr::null_mut()),
+            group: self.thread_group.as_ref().map(|group| group.0).unwrap_or(ptr::null_mut()),
+        };
+        let mut jni_env: *mut JNIEnv = ptr::null_mut();
+        unsafe {
+            let attach_current_thread = if self.daemon {
+                (**java_vm).AttachCurrentThreadAsDaemon
+            } else {
+                (**java_vm).AttachCurrentThread
+            };
+            let res = (opt_to_res(attach_current_thread)?)(
+                java_vm,
+                (&mut jni_env as *mut *mut JNIEnv) as *mut *mut c_void,
+                (&mut attach_args as *mut JavaVMAttachArgs) as *mut c_void);
+            if res == JNI_OK {
+                Ok(jni_env)
+            } else {
+                Err(J4RsError::JniError(format!("Could not attach the current thread to the Java VM: {}", res)))
+            }
+        }
+    }
+}
+
+// A global reference to a java.lang.ThreadGroup. Global references can be used by all the threads.
+#[derive(Debug)]
+struct ThreadGroupRef(jobject);
+
+unsafe impl Send for ThreadGroupRef {}
+
+unsafe impl Sync for ThreadGroupRef {}
+
+impl Drop for ThreadGroupRef {
+    fn drop(&mut self) {
+        match cache::get_thread_local_env_opt() {
+            Some(jni_env) => jni_utils::delete_java_ref(jni_env, self.0),
+            // The current thread cannot release the reference. Let an attached thread do it.
+            None => jni_utils::defer_java_ref_release(self.0),
+        }
+    }
+}
+
+/// A thread-safe handle of the Java VM.
+///
+/// Unlike `Jvm`, it can be cloned and shared between threads. Each thread can use it
+/// in order to retrieve a `Jvm` for itself, via `JvmHandle::jvm`.
+#[derive(Debug, Clone)]
+pub struct JvmHandle {
+    java_vm: *mut JavaVM,
+    attach_options: AttachOptions,
+}
+
+impl PartialEq for JvmHandle {
+    fn eq(&self, other: &JvmHandle) -> bool {
+        self.java_vm == other.java_vm
+    }
+}
+
+impl Eq for JvmHandle {}
+
+// The JavaVM pointer is valid for all the threads of the process.
+unsafe impl Send for JvmHandle {}
+
+unsafe impl Sync for JvmHandle {}
+
+impl JvmHandle {
+    pub(crate) fn from_env(jni_env: *mut JNIEnv) -> errors::Result<JvmHandle> {
+        unsafe {
+            let mut java_vm: *mut JavaVM = ptr::null_mut();
+            let res = (opt_to_res((**jni_env).GetJavaVM)?)(jni_env, &mut java_vm);
+            if res == JNI_OK && !java_vm.is_null() {
+                Ok(JvmHandle { java_vm, attach_options: AttachOptions::default() })
+            } else {
+                Err(J4RsError::JniError(format!("Could not retrieve the Java VM: {}", res)))
+            }
+        }
+    }
+
+    /// Returns the handle of the default JVM of the process.
+    ///
+    /// If there is no JVM created yet, one is created with the default `JvmBuilder` configuration.
+    /// If a JVM is already created, the handle refers to that JVM.
+    pub fn default_jvm() -> errors::Result<JvmHandle> {
+        let mut default_handle = DEFAULT_JVM_HANDLE.lock()?;
+        match default_handle.as_ref() {
+            Some(handle) => Ok(handle.clone()),
+            None => {
+                let handle = JvmBuilder::new().build()?.handle()?;
+                *default_handle = Some(handle.clone());
+                Ok(handle)
+            }
+        }
+    }
+
+    /// Returns a handle that attaches the threads using the provided `AttachOptions`.
+    pub fn with_attach_options(&self, attach_options: AttachOptions) -> JvmHandle {
+        JvmHandle {
+            java_vm: self.java_vm,
+            attach_options,
+        }
+    }
+
+    /// Returns a `Jvm` that can be used by the current thread.
+    ///
+    /// If the current thread is not attached to the Java VM, it gets attached and remains attached until it exits.
+    /// If it is already attached, the existing attachment is reused.
+    pub fn jvm(&self) -> errors::Result<Jvm> {
+        let jni_env = self.attach_current_thread()?;
+        let mut jvm = Jvm::try_from(jni_env)?;
+        // The thread is detached when it exits
+        jvm.detach_thread_on_drop(false);
+        Ok(jvm)
+    }
+
+    /// Starts a daemon thread that periodically releases the Java references of the `Instance`s
+    /// that were dropped by threads that are not attached to the JVM.
+    ///
+    /// Only one reaper thread is started per process; subsequent calls have no effect.
+    pub fn start_reference_reaper(&self, period: Duration) -> errors::Result<()> {
+        if REFERENCE_REAPER_STARTED.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let handle = self.with_attach_options(AttachOptions::new().daemon(true).thread_name("j4rs-reference-reaper"));
+        let spawned = thread::Builder::new()
+            .name("j4rs-reference-reaper".to_string())
+            .spawn(move || {
+                match handle.jvm() {
+                    Ok(jvm) => loop {
+                        thread::sleep(period);
+                        jni_utils::release_deferred_java_refs(jvm.jni_env);
+                    },
+                    Err(err) => error(&format!("The reference reaper could not attach to the JVM: {}", err)),
+                }
+            });
+        if let Err(err) = spawned {
+            REFERENCE_REAPER_STARTED.store(false, Ordering::SeqCst);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn attach_current_thread(&self) -> errors::Result<*mut JNIEnv> {
+        cache::ensure_jvm_not_destroyed()?;
+        unsafe {
+            let mut jni_env: *mut JNIEnv = ptr::null_mut();
+            let res = (opt_to_res((**self.java_vm).GetEnv)?)(
+                self.java_vm,
+                (&mut jni_env as *mut *mut JNIEnv) as *mut *mut c_void,
+                JNI_VERSION_1_8);
+            match res {
+                JNI_OK => Ok(jni_env),
+                JNI_EDETACHED => {
+                    debug("Attaching the current thread to the Java VM");
+                    let jni_env = self.attach_options.attach(self.java_vm)?;
+                    THREAD_ATTACHMENT.with(|attachment| {
+                        *attachment.borrow_mut() = Some(ThreadAttachment { java_vm: self.java_vm });
+                    });
+                    Ok(jni_env)
+                }
+                _ => Err(J4RsError::JniError(format!("Could not retrieve the JNIEnv of the current thread: {}", res))),
+            }
+        }
+    }
+}
+
+struct ThreadAttachment {
+    java_vm: *mut JavaVM,
+}
+
+impl Drop for ThreadAttachment {
+    fn drop(&mut self) {
+        if cache::is_jvm_destroyed() {
+            return;
+        }
+        debug("Detaching the exiting thread from the Java VM");
+        unsafe {
+            match (**self.java_vm).DetachCurrentThread {
+                Some(dct) => {
+                    (dct)(self.java_vm);
+                }
+                None => warn("Cannot detach the thread from the JVM"),
+            }
+        }
+    }
+}
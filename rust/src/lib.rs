@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// Allows the code generated by j4rs_derive to refer to this crate as `j4rs`.
+extern crate self as j4rs;
+extern crate glob;
 extern crate jni_sys;
 #[macro_use]
 extern crate lazy_static;
@@ -20,6 +23,7 @@ extern crate libc;
 extern crate log;
 extern crate serde;
 extern crate serde_json;
+extern crate toml;
 
 use std::mem;
 use std::os::raw::c_void;
@@ -27,27 +31,53 @@ use std::sync::mpsc::Sender;
 
 use jni_sys::{jlong, JNIEnv, jobject};
 
+use crate::task::RustTask;
+
 pub use self::api::Callback as Callback;
 pub use self::api::ClasspathEntry as ClasspathEntry;
 pub use self::api::Instance as Instance;
+pub use self::api::InstanceIterator as InstanceIterator;
 pub use self::api::InstanceReceiver as InstanceReceiver;
 pub use self::api::InvocationArg as InvocationArg;
 pub use self::api::JavaOpt as JavaOpt;
+pub use self::java_version::JavaVersion as JavaVersion;
+pub use self::java_version::JniVersion as JniVersion;
 pub use self::api::Jvm as Jvm;
 pub use self::api::JvmBuilder as JvmBuilder;
+pub use self::jvm_handle::AttachOptions as AttachOptions;
+pub use self::jvm_handle::JvmHandle as JvmHandle;
+pub use self::monitor::Monitor as Monitor;
+pub use self::api::Null as Null;
+pub use self::api::RustValuesIterator as RustValuesIterator;
+pub use self::api::WeakInstance as WeakInstance;
+pub use self::pojo::JavaClass as JavaClass;
+pub use self::pojo::JavaField as JavaField;
+#[doc(hidden)]
+pub use self::pojo::__private;
 pub use self::api_tweaks::{get_created_java_vms, set_java_vm};
 pub use self::provisioning::LocalJarArtifact as LocalJarArtifact;
 pub use self::provisioning::MavenArtifact as MavenArtifact;
 pub use self::provisioning::MavenArtifactRepo as MavenArtifactRepo;
+pub use self::provisioning::MavenDependency as MavenDependency;
 pub use self::provisioning::MavenSettings as MavenSettings;
 pub use self::jni_utils::jstring_to_rust_string as jstring_to_rust_string;
+pub use j4rs_derive::JavaClass;
 
 mod api;
 pub(crate) mod api_tweaks;
+mod config;
+pub mod diagnostics;
+#[cfg(feature = "embedded-jar")]
+mod embedded;
 pub mod errors;
+mod java_version;
 mod jni_utils;
+mod jvm_handle;
 mod logger;
+mod monitor;
+mod pojo;
 mod provisioning;
+mod task;
 mod utils;
 mod cache;
 
@@ -78,11 +108,23 @@ pub extern fn Java_org_astonbitecode_j4rs_api_invocation_NativeCallbackToRustCha
     }
 }
 
+#[no_mangle]
+pub extern fn Java_org_astonbitecode_j4rs_api_invocation_RustTask_runtask(jni_env: *mut JNIEnv, _class: *const c_void, task_address: jlong) -> jobject {
+    RustTask::run(jni_env, task_address as *const RustTask)
+}
+
+#[no_mangle]
+pub extern fn Java_org_astonbitecode_j4rs_api_invocation_RustTask_droptask(_jni_env: *mut JNIEnv, _class: *const c_void, task_address: jlong) {
+    RustTask::release(task_address as *mut RustTask)
+}
+
 #[cfg(test)]
 mod lib_unit_tests {
     use std::{thread, time};
     use std::convert::TryFrom;
     use std::path::MAIN_SEPARATOR;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::thread::JoinHandle;
 
     use fs_extra::remove_items;
@@ -90,7 +132,7 @@ mod lib_unit_tests {
     use crate::{LocalJarArtifact, MavenArtifactRepo, MavenSettings};
     use crate::provisioning::JavaArtifact;
 
-    use super::{ClasspathEntry, InvocationArg, Jvm, JvmBuilder, MavenArtifact};
+    use super::{AttachOptions, ClasspathEntry, Instance, InvocationArg, JavaClass, Jvm, JvmBuilder, JvmHandle, MavenArtifact, Null};
     use super::utils::jassets_path;
 
     #[test]
@@ -495,6 +537,56 @@ mod lib_unit_tests {
         let _ = remove_items(&vec![to_remove]);
     }
 
+    #[test]
+    fn deploy_maven_artifact_with_dependencies() {
+        let repository = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("resources").join("maven_repository");
+        let repository_path = repository.to_str().unwrap().replace('\\', "/");
+        let repository_url = format!("file://{}{}", if repository_path.starts_with('/') { "" } else { "/" }, repository_path);
+        // Only the fixture is searched, so that no artifact is fetched from the Maven Central
+        let jvm: Jvm = JvmBuilder::new()
+            .with_maven_settings(MavenSettings::without_maven_central(vec![MavenArtifactRepo::from(format!("fixture::{}", repository_url))]))
+            .build()
+            .unwrap();
+        let target_dir = std::env::temp_dir().join("j4rs_deploy_maven_artifact_with_dependencies");
+        let _ = std::fs::remove_dir_all(&target_dir);
+        let mut artifact = MavenArtifact::from("org.example.j4rs:app:1.0").with_dependencies();
+        artifact.base = target_dir.to_str().unwrap().to_string();
+
+        let tree = jvm.resolve_maven_dependencies(&artifact).unwrap();
+        assert_eq!(tree.to_string(), "org.example.j4rs:app:1.0
++- org.example.j4rs:library:2.0 (compile)
+|  \\- org.example.j4rs:deep:1.0 (compile)
+|     \\- org.example.j4rs:managed:1.5 (compile)
++- org.example.j4rs:from-bom:3.0 (compile)
++- org.example.j4rs:near:1.0 (runtime)
+\\- org.example.j4rs:interpolated:1.0 (compile)");
+        assert!(!target_dir.exists());
+
+        assert!(jvm.deploy_artifact(&artifact).is_ok());
+        let mut deployed: Vec<String> = std::fs::read_dir(&target_dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_str().unwrap().to_string())
+            .collect();
+        deployed.sort();
+        let mut expected: Vec<String> = tree.artifacts().iter()
+            .map(|coordinates| MavenArtifact::from(coordinates.as_str()).jar_name())
+            .collect();
+        expected.sort();
+        assert_eq!(deployed, expected);
+
+        // The errors of the resolution are reported
+        let missing = MavenArtifact::from("org.example.j4rs:missing:1.0").with_dependencies();
+        let errors = vec![
+            jvm.resolve_maven_dependencies(&missing).err().unwrap(),
+            jvm.deploy_artifact(&missing).err().unwrap(),
+        ];
+        for error in errors {
+            let message = format!("{}", error);
+            assert!(message.contains("Could not find the POM of org.example.j4rs:missing:1.0"), "{}", message);
+            assert!(message.contains(&repository_url), "{}", message);
+        }
+        let _ = std::fs::remove_dir_all(&target_dir);
+    }
+
     #[test]
     fn deploy_local_artifact() {
         let jvm: Jvm = super::new_jvm(Vec::new(), Vec::new()).unwrap();
@@ -738,4 +830,465 @@ mod lib_unit_tests {
         let ret: f64 = jvm.to_rust(i).unwrap();
         assert!(ret == 33.33_f64);
     }
+
+    #[test]
+    fn null_arguments_and_results() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let test_instance = jvm.create_instance("org.astonbitecode.j4rs.tests.MyTest", &[]).unwrap();
+
+        let null_arg = InvocationArg::create_null(Null::String);
+        let i = jvm.invoke(&test_instance, "getMyWithArgs", &[null_arg]).unwrap();
+        let s: String = jvm.to_rust(i).unwrap();
+        assert!(s == "THE DEFAULT CONSTRUCTOR WAS CALLEDnull");
+
+        let i = jvm.invoke(&test_instance, "echo", &[InvocationArg::create_null(Null::Integer)]).unwrap();
+        assert!(i.is_null().unwrap());
+        let opt: Option<i32> = jvm.to_rust(i).unwrap();
+        assert!(opt.is_none());
+
+        let i = jvm.invoke(&test_instance, "echo", &[InvocationArg::try_from(3_i32).unwrap()]).unwrap();
+        assert!(!i.is_null().unwrap());
+        let opt: Option<i32> = jvm.to_rust(i).unwrap();
+        assert!(opt == Some(3));
+
+        let i = jvm.invoke(&test_instance, "echo", &[InvocationArg::create_null(Null::Of("java.util.Date"))]).unwrap();
+        assert!(jvm.to_rust::<String>(i).is_err());
+    }
+
+    #[test]
+    fn optional_results() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+
+        let empty = jvm.invoke_static("java.util.Optional", "empty", &[]).unwrap();
+        assert!(!empty.is_null().unwrap());
+        let opt: Option<String> = jvm.to_rust(empty).unwrap();
+        assert!(opt.is_none());
+
+        let present = jvm.invoke_static("java.util.Optional", "of", &[InvocationArg::try_from("a string").unwrap()]).unwrap();
+        let opt: Option<String> = jvm.to_rust(present).unwrap();
+        assert!(opt == Some("a string".to_string()));
+    }
+
+    #[derive(Debug, PartialEq, JavaClass)]
+    #[j4rs(class = "org.astonbitecode.j4rs.tests.MyBean")]
+    struct MyBean {
+        name: String,
+        #[j4rs(rename = "age")]
+        years: i32,
+        active: bool,
+        tags: Option<Vec<String>>,
+        #[j4rs(skip)]
+        local: u8,
+    }
+
+    #[derive(Debug, JavaClass)]
+    #[j4rs(class = "org.astonbitecode.j4rs.tests.MyBean")]
+    struct MyBeanWithUnknownField {
+        name: String,
+        nick_name: String,
+    }
+
+    #[derive(Debug, JavaClass)]
+    #[j4rs(class = "org.astonbitecode.j4rs.tests.MyBean")]
+    struct MyBeanWithWrongType {
+        name: String,
+        tags: String,
+    }
+
+    #[derive(Debug, JavaClass)]
+    #[j4rs(class = "org.astonbitecode.j4rs.tests.MyBean")]
+    struct MyBeanWithMissingProperty {
+        name: String,
+        age: i32,
+    }
+
+    #[test]
+    fn java_class_derive() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let my_test = jvm.create_instance("org.astonbitecode.j4rs.tests.MyTest", &[]).unwrap();
+
+        let bean = MyBean { name: "j4rs".to_string(), years: 3, active: true, tags: Some(vec!["a".to_string()]), local: 7 };
+        let echoed = jvm.invoke(&my_test, "echo", &[InvocationArg::try_from(&bean).unwrap()]).unwrap();
+        let class_name: String = jvm.to_rust(jvm.invoke(&jvm.invoke(&echoed, "getClass", &[]).unwrap(), "getName", &[]).unwrap()).unwrap();
+        assert_eq!(class_name, "org.astonbitecode.j4rs.tests.MyBean");
+
+        let back = MyBean::try_from(echoed).unwrap();
+        assert_eq!(back, MyBean { local: 0, ..bean });
+
+        let tagless = MyBean { name: "j4rs".to_string(), years: 3, active: false, tags: None, local: 0 };
+        let echoed = jvm.invoke(&my_test, "echo", &[InvocationArg::try_from(tagless).unwrap()]).unwrap();
+        assert_eq!(MyBean::from_instance(&jvm, echoed).unwrap().tags, None);
+    }
+
+    #[test]
+    fn java_class_derive_mismatches() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let my_test = jvm.create_instance("org.astonbitecode.j4rs.tests.MyTest", &[]).unwrap();
+
+        let unknown = MyBeanWithUnknownField { name: "j4rs".to_string(), nick_name: "j".to_string() };
+        let error = format!("{}", InvocationArg::try_from(unknown).err().unwrap());
+        assert!(error.contains("`nick_name`") && error.contains("`nickName`"), "{}", error);
+
+        let wrong = MyBeanWithWrongType { name: "j4rs".to_string(), tags: "a".to_string() };
+        let error = format!("{}", InvocationArg::try_from(wrong).err().unwrap());
+        assert!(error.contains("`tags`") && error.contains("java.util.List"), "{}", error);
+
+        // Null Java properties cannot be mapped to Rust fields that are not Options
+        let bean = jvm.create_instance("org.astonbitecode.j4rs.tests.MyBean", &[]).unwrap();
+        let echoed = jvm.invoke(&my_test, "echo", &[InvocationArg::from(bean)]).unwrap();
+        let error = format!("{}", MyBeanWithMissingProperty::try_from(echoed).err().unwrap());
+        assert!(error.contains("`name`") && error.contains("String"), "{}", error);
+    }
+
+    #[test]
+    fn iterate_lazily() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let args: Vec<InvocationArg> = (1..=5).map(|i| InvocationArg::try_from(i).unwrap()).collect();
+        let list = jvm.create_java_list("java.lang.Integer", &args).unwrap();
+
+        // Instances, one by one
+        let instances: Vec<Instance> = jvm.iterate(&list).unwrap().map(|res| res.unwrap()).collect();
+        assert_eq!(instances.len(), 5);
+        let first: i32 = jvm.to_rust(jvm.clone_instance(&instances[0]).unwrap()).unwrap();
+        assert_eq!(first, 1);
+
+        // Rust values, in batches that do not divide the elements evenly
+        let values: Vec<i32> = jvm.iterate(&list).unwrap()
+            .batch_size(2)
+            .into_rust()
+            .map(|res| res.unwrap())
+            .collect();
+        assert_eq!(values, vec![1, 2, 3, 4, 5]);
+        let values: Vec<i32> = jvm.iterate(&list).unwrap()
+            .batch_size(usize::MAX)
+            .into_rust()
+            .map(|res| res.unwrap())
+            .collect();
+        assert_eq!(values, vec![1, 2, 3, 4, 5]);
+
+        // Streams are consumed lazily
+        let stream = jvm.invoke(&list, "stream", &[]).unwrap();
+        let mut iter = jvm.iterate(&stream).unwrap().batch_size(10).into_rust::<i32>();
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert_eq!(iter.count(), 4);
+
+        // Iterators
+        let iterator = jvm.chain(jvm.clone_instance(&list).unwrap()).cast("java.util.List").unwrap().invoke("iterator", &[]).unwrap().collect();
+        assert_eq!(jvm.iterate(&iterator).unwrap().into_rust::<i32>().count(), 5);
+
+        // Non iterable Instances
+        let not_iterable = jvm.create_instance("java.lang.String", &[InvocationArg::try_from("a").unwrap()]).unwrap();
+        assert!(jvm.iterate(&not_iterable).is_err());
+    }
+
+    #[test]
+    fn unicode_strings() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let my_test = jvm.create_instance("org.astonbitecode.j4rs.tests.MyTest", &[]).unwrap();
+
+        let s = "a\u{0}b 😀 ελληνικά";
+        // The Java String contains the NUL and the surrogate pair of the emoji
+        let length: i32 = jvm.to_rust(jvm.invoke(&jvm.create_instance("java.lang.String", &[InvocationArg::try_from(s).unwrap()]).unwrap(), "length", &[]).unwrap()).unwrap();
+        assert_eq!(length as usize, s.encode_utf16().count());
+
+        // Basic Strings
+        let echoed: String = jvm.to_rust(jvm.invoke(&my_test, "echo", &[InvocationArg::try_from(s).unwrap()]).unwrap()).unwrap();
+        assert_eq!(echoed, s);
+
+        // Serialized values
+        let values = vec![s.to_string(), "🦀".to_string()];
+        let list = jvm.create_java_list("java.lang.String", &[InvocationArg::try_from(&values[0]).unwrap(), InvocationArg::try_from(&values[1]).unwrap()]).unwrap();
+        let echoed: Vec<String> = jvm.to_rust(list).unwrap();
+        assert_eq!(echoed, values);
+        let arg = InvocationArg::new(&values, "java.util.ArrayList");
+        let echoed: Vec<String> = jvm.to_rust(jvm.invoke(&my_test, "echo", &[arg]).unwrap()).unwrap();
+        assert_eq!(echoed, values);
+
+        // Strings created in the Java world
+        let emoji: String = jvm.to_rust(jvm.invoke_static("java.lang.Character", "toString", &[InvocationArg::try_from(0x1F600).unwrap().into_primitive().unwrap()]).unwrap()).unwrap();
+        assert_eq!(emoji, "😀");
+    }
+
+    #[test]
+    fn jvm_handle_in_threads() {
+        let handle = JvmBuilder::new().build().unwrap().handle().unwrap();
+        assert_eq!(JvmHandle::default_jvm().unwrap(), handle);
+
+        let workers: Vec<JoinHandle<i32>> = (0..4)
+            .map(|i| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    // The same thread may get a Jvm many times
+                    let sum: i32 = (0..3).map(|j| {
+                        let jvm = handle.jvm().unwrap();
+                        let instance = jvm.invoke_static("java.lang.Integer", "valueOf", &[InvocationArg::try_from(i + j).unwrap().into_primitive().unwrap()]).unwrap();
+                        jvm.to_rust::<i32>(instance).unwrap()
+                    }).sum();
+                    sum
+                })
+            })
+            .collect();
+        let total: i32 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        assert_eq!(total, (0..4).map(|i| 3 * i + 3).sum::<i32>());
+    }
+
+    #[test]
+    fn attach_named_daemon_threads() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let group = jvm.create_instance("java.lang.ThreadGroup", &[InvocationArg::try_from("rust-group").unwrap()]).unwrap();
+        let options = AttachOptions::new()
+            .daemon(true)
+            .thread_name("rust-worker-ü")
+            .thread_group(&jvm, &group)
+            .unwrap();
+        let handle = jvm.handle().unwrap().with_attach_options(options.clone());
+
+        let from_handle = thread::spawn(move || {
+            let jvm = handle.jvm().unwrap();
+            describe_current_thread(&jvm)
+        }).join().unwrap();
+        assert_eq!(from_handle, ("rust-worker-ü".to_string(), true, "rust-group".to_string()));
+
+        let from_builder = thread::spawn(move || {
+            let jvm = JvmBuilder::new().with_attach_options(options.daemon(false)).build().unwrap();
+            describe_current_thread(&jvm)
+        }).join().unwrap();
+        assert_eq!(from_builder, ("rust-worker-ü".to_string(), false, "rust-group".to_string()));
+
+        // The thread group is released along with the last clone of the options
+        super::diagnostics::assert_no_leaks(|| {
+            let options = AttachOptions::new().thread_group(&jvm, &group).unwrap();
+            let cloned = options.clone();
+            drop(options);
+            drop(cloned);
+        });
+    }
+
+    #[test]
+    fn local_frames() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        jvm.ensure_local_capacity(64).unwrap();
+
+        // Instances survive the frames
+        let instances: Vec<Instance> = (0..1000).map(|i| {
+            jvm.with_local_frame(16, |jvm| {
+                jvm.invoke_static("java.lang.Integer", "valueOf", &[InvocationArg::try_from(i).unwrap().into_primitive().unwrap()])
+            }).unwrap()
+        }).collect();
+        let last: i32 = jvm.to_rust(jvm.clone_instance(&instances[999]).unwrap()).unwrap();
+        assert_eq!(last, 999);
+
+        // Errors are propagated and the frame is popped
+        let res: super::errors::Result<()> = jvm.with_local_frame(16, |_| Err(super::errors::J4RsError::GeneralError("failure".to_string())));
+        assert!(res.is_err());
+
+        // The results are promoted out of the frame, while the local references of the frame are freed
+        let promoted = jvm.with_local_frame(16, |jvm| {
+            let list = jvm.create_instance("java.util.ArrayList", &[])?;
+            for i in 0..100 {
+                jvm.invoke(&list, "add", &[InvocationArg::try_from(i)?])?;
+            }
+            let first = jvm.invoke(&list, "get", &[InvocationArg::try_from(0)?.into_primitive()?])?;
+            let size = jvm.invoke(&list, "size", &[])?;
+            Ok(vec![first, size])
+        }).unwrap();
+        let values: Vec<i32> = promoted.into_iter().map(|instance| jvm.to_rust(instance).unwrap()).collect();
+        assert_eq!(values, vec![0, 100]);
+    }
+
+    #[test]
+    fn weak_instances() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+
+        // The class that the object is treated as is kept
+        let list = jvm.create_java_list("java.lang.Integer", &[InvocationArg::try_from(1).unwrap()]).unwrap();
+        let list = jvm.cast(&list, "java.util.List").unwrap();
+        let weak = list.downgrade().unwrap();
+        let upgraded = weak.upgrade().unwrap().unwrap();
+        let size: i32 = jvm.to_rust(jvm.invoke(&upgraded, "size", &[]).unwrap()).unwrap();
+        assert_eq!(size, 1);
+
+        // Weak references do not keep the objects alive
+        let instance = jvm.create_instance("java.lang.StringBuilder", &[InvocationArg::try_from("weak").unwrap()]).unwrap();
+        let weak = instance.downgrade().unwrap();
+        drop(instance);
+        let mut collected = false;
+        for _ in 0..20 {
+            jvm.invoke_static("java.lang.System", "gc", &[]).unwrap();
+            if weak.upgrade().unwrap().is_none() {
+                collected = true;
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(50));
+        }
+        assert!(collected);
+    }
+
+    #[test]
+    fn synchronized_blocks() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let list = jvm.invoke_static("java.util.Collections", "synchronizedList", &[InvocationArg::from(jvm.create_instance("java.util.ArrayList", &[]).unwrap())]).unwrap();
+        assert!(!holds_lock(&jvm, &list));
+
+        let held = jvm.synchronized(&list, |_| Ok(holds_lock(&jvm, &list))).unwrap();
+        assert!(held);
+        assert!(!holds_lock(&jvm, &list));
+
+        // The monitor is exited on errors and panics
+        let res: super::errors::Result<()> = jvm.synchronized(&list, |_| Err(super::errors::J4RsError::GeneralError("failure".to_string())));
+        assert!(res.is_err());
+        assert!(!holds_lock(&jvm, &list));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _: super::errors::Result<()> = jvm.synchronized(&list, |_| panic!("panic while synchronized"));
+        }));
+        assert!(res.is_err());
+        assert!(!holds_lock(&jvm, &list));
+
+        // Waiting and notifying
+        let to_notify = jvm.clone_instance(&list).unwrap();
+        let start = time::Instant::now();
+        jvm.synchronized(&list, |monitor| {
+            let notifier = thread::spawn(move || {
+                let jvm = Jvm::attach_thread().unwrap();
+                jvm.synchronized(&to_notify, |monitor| monitor.notify_all()).unwrap();
+            });
+            monitor.wait_timeout(time::Duration::from_secs(10))?;
+            notifier.join().unwrap();
+            Ok(())
+        }).unwrap();
+        assert!(start.elapsed() < time::Duration::from_secs(10));
+
+        // Timeouts below a millisecond do not wait forever
+        let (tx, rx) = std::sync::mpsc::channel();
+        let to_wait = jvm.clone_instance(&list).unwrap();
+        thread::spawn(move || {
+            let jvm = Jvm::attach_thread().unwrap();
+            let res = jvm.synchronized(&to_wait, |monitor| monitor.wait_timeout(time::Duration::from_micros(100)));
+            tx.send(res.is_ok()).unwrap();
+        });
+        assert!(rx.recv_timeout(time::Duration::from_secs(10)).unwrap());
+    }
+
+    #[test]
+    fn rust_closures_on_java_threads() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let executor = jvm.invoke_static("java.util.concurrent.Executors", "newFixedThreadPool", &[InvocationArg::try_from(2).unwrap().into_primitive().unwrap()]).unwrap();
+        let executor = jvm.cast(&executor, "java.util.concurrent.ExecutorService").unwrap();
+
+        // Runnables
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_in_java = runs.clone();
+        let runnable = jvm.create_runnable(move |_| {
+            runs_in_java.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }).unwrap();
+        for _ in 0..3 {
+            let future = jvm.invoke(&executor, "submit", &[InvocationArg::from(jvm.clone_instance(&runnable).unwrap())]).unwrap();
+            jvm.invoke(&future, "get", &[]).unwrap();
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // Callables, running on the Java threads
+        let callable = jvm.create_callable(|jvm| {
+            let thread = jvm.invoke_static("java.lang.Thread", "currentThread", &[])?;
+            let name: String = jvm.to_rust(jvm.invoke(&thread, "getName", &[])?)?;
+            InvocationArg::try_from(name)
+        }).unwrap();
+        let future = jvm.invoke(&executor, "submit", &[InvocationArg::from(callable)]).unwrap();
+        let thread_name: String = jvm.to_rust(jvm.invoke(&future, "get", &[]).unwrap()).unwrap();
+        assert!(thread_name.starts_with("pool-"), "{}", thread_name);
+
+        // Failures are thrown to Java
+        let failing = jvm.create_callable(|_| Err(super::errors::J4RsError::GeneralError("failure".to_string()))).unwrap();
+        let future = jvm.invoke(&executor, "submit", &[InvocationArg::from(failing)]).unwrap();
+        assert!(jvm.invoke(&future, "get", &[]).is_err());
+
+        jvm.invoke(&executor, "shutdown", &[]).unwrap();
+
+        // The closures are released when the tasks are closed or garbage collected
+        struct Released(Arc<AtomicUsize>);
+        impl Drop for Released {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let releases = Arc::new(AtomicUsize::new(0));
+        let released = Released(releases.clone());
+        let closed = jvm.create_runnable(move |_| {
+            let _ = &released;
+            Ok(())
+        }).unwrap();
+        let closeable = jvm.cast(&closed, "java.lang.AutoCloseable").unwrap();
+        jvm.invoke(&closeable, "close", &[]).unwrap();
+        assert_eq!(releases.load(Ordering::SeqCst), 1);
+        assert!(jvm.invoke(&closed, "run", &[]).is_err());
+        jvm.invoke(&closeable, "close", &[]).unwrap();
+        assert_eq!(releases.load(Ordering::SeqCst), 1);
+
+        let released = Released(releases.clone());
+        let collected = jvm.create_runnable(move |_| {
+            let _ = &released;
+            Ok(())
+        }).unwrap();
+        drop(collected);
+        for _ in 0..20 {
+            jvm.invoke_static("java.lang.System", "gc", &[]).unwrap();
+            if releases.load(Ordering::SeqCst) == 2 {
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(50));
+        }
+        assert_eq!(releases.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn leak_diagnostics() {
+        let jvm: Jvm = JvmBuilder::new().build().unwrap();
+        let test_class = "org.astonbitecode.j4rs.tests.MySecondTest";
+
+        // Nothing leaks when everything is dropped inside the block
+        super::diagnostics::assert_no_leaks(|| {
+            let list = jvm.create_instance("java.util.ArrayList", &[]).unwrap();
+            jvm.invoke(&list, "add", &[InvocationArg::try_from("a").unwrap()]).unwrap();
+            let size: i32 = jvm.to_rust(jvm.invoke(&list, "size", &[]).unwrap()).unwrap();
+            assert_eq!(size, 1);
+            let tester = jvm.create_instance(test_class, &[]).unwrap();
+            let _rx = jvm.init_callback_channel(&tester).unwrap();
+        });
+
+        // What outlives the block is reported
+        let (kept, report) = super::diagnostics::leaks_of(|| {
+            let list = jvm.create_instance("java.util.ArrayList", &[]).unwrap();
+            let size = jvm.invoke(&list, "size", &[]).unwrap();
+            let tester = jvm.create_instance(test_class, &[]).unwrap();
+            let rx = jvm.init_callback_channel(&tester).unwrap();
+            (list, size, rx)
+        });
+        assert_eq!(report.live_instances(), 2);
+        assert_eq!(report.live_global_refs(), 2);
+        assert_eq!(report.live_callback_channels(), 1);
+        assert!(report.instances().iter().any(|group| group.class_name() == "java.util.ArrayList"));
+        assert!(report.instances().iter().all(|group| group.call_site().starts_with(file!())));
+        assert_eq!(report.callback_channels()[0].class_name(), test_class);
+        assert!(format!("{}", report).contains("2 Instances, 2 global references, 1 callback channels"));
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            super::diagnostics::assert_no_leaks(|| jvm.create_instance("java.util.ArrayList", &[]).unwrap())
+        }));
+        assert!(res.is_err());
+        drop(kept);
+    }
+
+    fn holds_lock(jvm: &Jvm, instance: &Instance) -> bool {
+        let object = jvm.cast(instance, "java.lang.Object").unwrap();
+        jvm.to_rust(jvm.invoke_static("java.lang.Thread", "holdsLock", &[InvocationArg::from(object)]).unwrap()).unwrap()
+    }
+
+    fn describe_current_thread(jvm: &Jvm) -> (String, bool, String) {
+        let thread = jvm.invoke_static("java.lang.Thread", "currentThread", &[]).unwrap();
+        let name: String = jvm.to_rust(jvm.invoke(&thread, "getName", &[]).unwrap()).unwrap();
+        let daemon: bool = jvm.to_rust(jvm.invoke(&thread, "isDaemon", &[]).unwrap()).unwrap();
+        let group: String = jvm.chain(thread).invoke("getThreadGroup", &[]).unwrap().invoke("getName", &[]).unwrap().to_rust().unwrap();
+        (name, daemon, group)
+    }
 }
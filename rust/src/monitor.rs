@@ -0,0 +1,128 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use jni_sys::{JNI_OK, jobject};
+
+use crate::{cache, errors, Instance, InvocationArg, jni_utils, Jvm};
+use crate::errors::{J4RsError, opt_to_res};
+use crate::logger::{debug, error};
+
+/// The monitor of a Java object, held by the current thread.
+///
+/// It is entered by `Jvm::synchronized` and exited when the function that is passed there returns.
+pub struct Monitor<'a> {
+    jvm: &'a Jvm,
+    // A global reference to the Java object whose monitor is held
+    object: jobject,
+    // The Java object, treated as a java.lang.Object
+    as_object: Instance,
+}
+
+impl<'a> Monitor<'a> {
+    // Enters the monitor of the Java object of the instance, blocking until it is available.
+    pub(crate) fn enter(jvm: &'a Jvm, instance: &Instance) -> errors::Result<Monitor<'a>> {
+        let monitor_enter = opt_to_res(cache::get_jni_monitor_enter())?;
+        let as_object = jvm.cast(instance, "java.lang.Object")?;
+        let object = instance.wrapped_object_global_ref(jvm.jni_env)?;
+        let res = unsafe { (monitor_enter)(jvm.jni_env, object) };
+        let entered = Jvm::do_return(jvm.jni_env, res).and_then(|res| {
+            if res == JNI_OK {
+                Ok(())
+            } else {
+                Err(J4RsError::JniError(format!("Could not enter the monitor of an instance of {}: {}", instance.class_name(), res)))
+            }
+        });
+        if let Err(error) = entered {
+            jni_utils::delete_java_ref(jvm.jni_env, object);
+            return Err(error);
+        }
+        debug(&format!("Entered the monitor of an instance of {}", instance.class_name()));
+        Ok(Monitor { jvm, object, as_object })
+    }
+
+    /// Releases the monitor and waits until another thread notifies it, as with `Object.wait()`.
+    pub fn wait(&self) -> errors::Result<()> {
+        self.jvm.invoke(&self.as_object, "wait", &[])?;
+        Ok(())
+    }
+
+    /// Releases the monitor and waits until another thread notifies it or the timeout elapses, as with `Object.wait(long)`.
+    ///
+    /// A timeout of zero waits forever.
+    /// Non-zero timeouts are rounded up to whole milliseconds, so that they do not become zero.
+    pub fn wait_timeout(&self, timeout: Duration) -> errors::Result<()> {
+        let millis = InvocationArg::try_from(wait_millis(timeout))?.into_primitive()?;
+        self.jvm.invoke(&self.as_object, "wait", &[millis])?;
+        Ok(())
+    }
+
+    /// Wakes up one of the threads that are waiting on the monitor, as with `Object.notify()`.
+    pub fn notify(&self) -> errors::Result<()> {
+        self.jvm.invoke(&self.as_object, "notify", &[])?;
+        Ok(())
+    }
+
+    /// Wakes up all the threads that are waiting on the monitor, as with `Object.notifyAll()`.
+    pub fn notify_all(&self) -> errors::Result<()> {
+        self.jvm.invoke(&self.as_object, "notifyAll", &[])?;
+        Ok(())
+    }
+}
+
+impl<'a> Drop for Monitor<'a> {
+    fn drop(&mut self) {
+        debug("Exiting a monitor");
+        if cache::is_jvm_destroyed() {
+            return;
+        }
+        match cache::get_jni_monitor_exit() {
+            Some(monitor_exit) => unsafe {
+                if (monitor_exit)(self.jvm.jni_env, self.object) != JNI_OK {
+                    error("Could not exit a monitor");
+                }
+            },
+            None => error("Could not exit a monitor. This may lead to deadlocks"),
+        }
+        jni_utils::delete_java_ref(self.jvm.jni_env, self.object);
+    }
+}
+
+// The milliseconds of `Object.wait(long)` for a timeout: non-zero timeouts are at least one millisecond,
+// and the ones that do not fit in a long are saturated.
+fn wait_millis(timeout: Duration) -> i64 {
+    if timeout.is_zero() {
+        0
+    } else {
+        let millis = timeout.as_nanos().div_ceil(1_000_000);
+        i64::try_from(millis).unwrap_or(i64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod monitor_unit_tests {
+    use super::*;
+
+    #[test]
+    fn wait_timeout_millis() {
+        assert_eq!(wait_millis(Duration::ZERO), 0);
+        assert_eq!(wait_millis(Duration::from_nanos(1)), 1);
+        assert_eq!(wait_millis(Duration::from_micros(100)), 1);
+        assert_eq!(wait_millis(Duration::from_micros(1500)), 2);
+        assert_eq!(wait_millis(Duration::from_secs(10)), 10_000);
+        assert_eq!(wait_millis(Duration::MAX), i64::MAX);
+    }
+}
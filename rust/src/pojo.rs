@@ -0,0 +1,219 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::api::{Instance, InvocationArg, Jvm};
+use crate::errors;
+use crate::errors::J4RsError;
+
+const CODEC_CLASS: &str = "org.astonbitecode.j4rs.json.Codec";
+
+lazy_static! {
+    // The properties of the Java classes, as these are described by the Java world.
+    static ref JAVA_PROPERTIES: Mutex<HashMap<String, Vec<PropertyDescription>>> = Mutex::new(HashMap::new());
+}
+
+/// A Rust type that is mapped to a Java class.
+///
+/// It is not meant to be implemented manually, but rather via `#[derive(JavaClass)]`.
+pub trait JavaClass: Sized {
+    /// The fully qualified name of the Java class.
+    fn java_class_name() -> &'static str;
+
+    /// The mapping of the Rust fields to the Java properties.
+    fn java_fields() -> &'static [JavaField];
+
+    #[doc(hidden)]
+    fn to_java_properties(&self) -> errors::Result<__private::JavaProperties>;
+
+    #[doc(hidden)]
+    fn from_java_properties(properties: __private::JavaProperties) -> errors::Result<Self>;
+
+    /// Creates a Rust value out of a Java Instance.
+    fn from_instance(jvm: &Jvm, instance: Instance) -> errors::Result<Self> {
+        match jvm.to_rust::<Value>(instance)? {
+            Value::Object(properties) => Self::from_java_properties(properties),
+            other => Err(J4RsError::ParseError(format!(
+                "Cannot create a {} from the Java value {}: A {} object was expected",
+                std::any::type_name::<Self>(), other, Self::java_class_name()))),
+        }
+    }
+}
+
+/// The mapping of a Rust field to a Java property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JavaField {
+    pub rust_name: &'static str,
+    pub java_name: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PropertyDescription {
+    name: String,
+    #[serde(rename = "type")]
+    type_name: String,
+    kind: String,
+}
+
+impl PropertyDescription {
+    fn accepts(&self, value: &Value) -> bool {
+        matches!((self.kind.as_str(), value),
+            (_, Value::Null) |
+            ("boolean", Value::Bool(_)) | ("boolean", Value::String(_)) | ("boolean", Value::Number(_)) |
+            ("number", Value::Number(_)) | ("number", Value::String(_)) |
+            ("string", Value::String(_)) | ("string", Value::Number(_)) | ("string", Value::Bool(_)) |
+            ("array", Value::Array(_)) |
+            ("object", Value::Object(_)) |
+            ("any", _))
+    }
+}
+
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Retrieves the properties of a Java class, caching them for subsequent calls
+fn java_properties_of(class_name: &str) -> errors::Result<Vec<PropertyDescription>> {
+    if let Some(properties) = JAVA_PROPERTIES.lock()?.get(class_name) {
+        return Ok(properties.clone());
+    }
+    let jvm = Jvm::attach_thread()?;
+    let descriptions = jvm.invoke_static(CODEC_CLASS, "describeProperties", &[InvocationArg::try_from(class_name)?])
+        .map_err(|error| J4RsError::ParseError(format!("Could not retrieve the properties of the Java class {}: {}", class_name, error)))?;
+    let properties: Vec<PropertyDescription> = jvm.to_rust(descriptions)?;
+    JAVA_PROPERTIES.lock()?.insert(class_name.to_string(), properties.clone());
+    Ok(properties)
+}
+
+fn validate(class_name: &str, fields: &[JavaField], properties: &__private::JavaProperties, java_properties: &[PropertyDescription]) -> errors::Result<()> {
+    for field in fields {
+        let value = properties.get(field.java_name).unwrap_or(&Value::Null);
+        match java_properties.iter().find(|p| p.name == field.java_name) {
+            Some(java_property) if java_property.accepts(value) => {}
+            Some(java_property) => {
+                return Err(J4RsError::ParseError(format!(
+                    "The Rust field `{}` is mapped to the property `{}` of {}, which is of type {}, but a {} value was provided: {}",
+                    field.rust_name, field.java_name, class_name, java_property.type_name, json_kind(value), value)));
+            }
+            None => {
+                let available: Vec<&str> = java_properties.iter().map(|p| p.name.as_str()).collect();
+                return Err(J4RsError::ParseError(format!(
+                    "The Rust field `{}` is mapped to the property `{}`, which does not exist in {}. Available properties: [{}]",
+                    field.rust_name, field.java_name, class_name, available.join(", "))));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+
+    pub type JavaProperties = Map<String, Value>;
+
+    pub fn to_property<T: Serialize>(value: &T, rust_name: &str, java_name: &str, class_name: &str) -> errors::Result<Value> {
+        serde_json::to_value(value)
+            .map_err(|error| J4RsError::ParseError(format!(
+                "Could not serialize the Rust field `{}` to the property `{}` of {}: {}", rust_name, java_name, class_name, error)))
+    }
+
+    pub fn from_property<T: DeserializeOwned>(properties: &mut JavaProperties, rust_name: &str, java_name: &str, class_name: &str) -> errors::Result<T> {
+        match properties.remove(java_name) {
+            Some(value) => {
+                let kind = json_kind(&value);
+                serde_json::from_value(value)
+                    .map_err(|error| J4RsError::ParseError(format!(
+                        "The property `{}` of {} is a {} value that cannot be used for the Rust field `{}` of type {}: {}",
+                        java_name, class_name, kind, rust_name, std::any::type_name::<T>(), error)))
+            }
+            // A missing property is fine only for the fields that can hold nulls (i.e. Options)
+            None => serde_json::from_value(Value::Null)
+                .map_err(|_| J4RsError::ParseError(format!(
+                    "The property `{}` of {} is missing, while it is needed for the Rust field `{}`",
+                    java_name, class_name, rust_name))),
+        }
+    }
+
+    pub fn to_invocation_arg<T: JavaClass>(value: &T) -> errors::Result<InvocationArg> {
+        let class_name = T::java_class_name();
+        let properties = value.to_java_properties()?;
+        validate(class_name, T::java_fields(), &properties, &java_properties_of(class_name)?)?;
+        Ok(InvocationArg::Rust {
+            json: serde_json::to_string(&Value::Object(properties))?,
+            class_name: class_name.to_string(),
+            serialized: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod pojo_unit_tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn description(name: &str, kind: &str) -> PropertyDescription {
+        PropertyDescription { name: name.to_string(), type_name: "t".to_string(), kind: kind.to_string() }
+    }
+
+    #[test]
+    fn property_kinds() {
+        assert!(description("a", "number").accepts(&json!(1)));
+        assert!(description("a", "number").accepts(&Value::Null));
+        assert!(!description("a", "number").accepts(&json!([1])));
+        assert!(description("a", "string").accepts(&json!("s")));
+        assert!(!description("a", "string").accepts(&json!({"a": 1})));
+        assert!(description("a", "any").accepts(&json!({"a": 1})));
+        assert!(!description("a", "array").accepts(&json!("s")));
+    }
+
+    #[test]
+    fn validation() {
+        let fields = [JavaField { rust_name: "my_age", java_name: "myAge" }];
+        let mut properties = Map::new();
+        properties.insert("myAge".to_string(), json!(3));
+
+        assert!(validate("C", &fields, &properties, &[description("myAge", "number")]).is_ok());
+
+        let res = validate("C", &fields, &properties, &[description("myAge", "array")]);
+        assert!(format!("{}", res.err().unwrap()).contains("`my_age`"));
+
+        let res = validate("C", &fields, &properties, &[description("age", "number")]);
+        assert!(format!("{}", res.err().unwrap()).contains("Available properties: [age]"));
+    }
+
+    #[test]
+    fn missing_properties() {
+        let mut properties = Map::new();
+        let res: errors::Result<Option<i32>> = __private::from_property(&mut properties, "a", "a", "C");
+        assert_eq!(res.unwrap(), None);
+        let res: errors::Result<i32> = __private::from_property(&mut properties, "a", "a", "C");
+        assert!(format!("{}", res.err().unwrap()).contains("is missing"));
+    }
+}
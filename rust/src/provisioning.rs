@@ -13,8 +13,13 @@
 // limitations under the License.
 
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::{env, fmt, fs, io};
+use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
+
+use crate::errors;
+use crate::errors::J4RsError;
 use crate::utils;
 
 const MAVEN_CENTRAL: &'static str = "MavenCentral::https://repo.maven.apache.org/maven2";
@@ -74,17 +79,118 @@ impl From<String> for LocalJarArtifact {
 
 /// Represents an Artifact that can be fetched by a remote Maven repository.
 /// It can loaded and used by j4rs by calling the `JVM::deploy_artifact` method.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MavenArtifact {
     pub(crate) base: String,
     pub(crate) group: String,
     pub(crate) id: String,
     pub(crate) version: String,
     pub(crate) qualifier: String,
+    pub(crate) with_dependencies: bool,
 }
 
 impl JavaArtifact for MavenArtifact {}
 
+impl MavenArtifact {
+    /// Makes `Jvm::deploy_artifact` deploy the transitive dependencies of the artifact as well.
+    ///
+    /// The dependencies are resolved from the POMs that are found in the Maven repositories.
+    /// See `Jvm::resolve_maven_dependencies` for the resolution rules.
+    pub fn with_dependencies(mut self) -> MavenArtifact {
+        self.with_dependencies = true;
+        self
+    }
+
+    // The name of the jar of the artifact, the same with the one that the Java deployer creates
+    pub(crate) fn jar_name(&self) -> String {
+        if self.qualifier.is_empty() {
+            format!("{}-{}.jar", self.id, self.version)
+        } else {
+            format!("{}-{}-{}.jar", self.id, self.version, self.qualifier)
+        }
+    }
+
+    // The URL of the jar of the artifact in a Maven repository, the same with the one that the Java deployer uses
+    fn url_in(&self, repo_uri: &str) -> String {
+        format!("{}/{}/{}/{}/{}", repo_uri.trim_end_matches('/'), self.group.replace('.', "/"), self.id, self.version, self.jar_name())
+    }
+
+    // The location of the jar of the artifact in a local Maven repository
+    fn path_in(&self, local_repository: &Path) -> PathBuf {
+        let mut path = local_repository.to_path_buf();
+        path.extend(self.group.split('.'));
+        path.push(&self.id);
+        path.push(&self.version);
+        path.push(self.jar_name());
+        path
+    }
+}
+
+// The local Maven repository of the user
+pub(crate) fn local_maven_repository() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".m2").join("repository"))
+}
+
+// Returns the jar of an artifact that is deployed in the target directory.
+// If the artifact is not deployed there, it is copied from the local Maven repository, if it is found,
+// or else it is fetched from the first of the Maven repositories that contains it.
+pub(crate) fn provision_artifact(artifact: &MavenArtifact, local_repository: Option<&Path>, repos: &[MavenArtifactRepo], target_dir: &Path) -> errors::Result<PathBuf> {
+    let target = target_dir.join(artifact.jar_name());
+    if target.is_file() {
+        return Ok(target);
+    }
+    fs::create_dir_all(target_dir)?;
+    if let Some(cached) = local_repository.map(|local_repository| artifact.path_in(local_repository)) {
+        if cached.is_file() {
+            fs::copy(&cached, &target)?;
+            return Ok(target);
+        }
+    }
+    let mut failures = Vec::new();
+    for repo in repos {
+        match fetch_from_repository(artifact, repo, &target) {
+            Ok(()) => return Ok(target),
+            Err(error) => failures.push(format!("{}", error)),
+        }
+    }
+    Err(J4RsError::GeneralError(format!(
+        "The artifact {}:{}:{} is not deployed in {} and could not be fetched from the Maven repositories: [{}]. \
+        It can be deployed using Jvm::deploy_artifact, e.g. in a build script",
+        artifact.group, artifact.id, artifact.version, target_dir.display(), failures.join(", "))))
+}
+
+// Fetches the jar of an artifact from a `file`, `http` or `https` Maven repository
+fn fetch_from_repository(artifact: &MavenArtifact, repo: &MavenArtifactRepo, target: &Path) -> errors::Result<()> {
+    let url = artifact.url_in(&repo.uri);
+    // The jar is written next to the target and moved in place once it is complete
+    let partial = target.with_extension(format!("{}.part", std::process::id()));
+    let written = if let Some(path) = url.strip_prefix("file://") {
+        fs::copy(path, &partial)
+            .map(|_| ())
+            .map_err(|error| J4RsError::GeneralError(format!("{}: {}", url, error)))
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        ureq::get(&url).call()
+            .map_err(|error| J4RsError::GeneralError(format!("{}: {}", url, error)))
+            .and_then(|response| {
+                let mut file = fs::File::create(&partial)?;
+                io::copy(&mut response.into_reader(), &mut file)
+                    .map(|_| ())
+                    .map_err(|error| J4RsError::GeneralError(format!("{}: {}", url, error)))
+            })
+    } else {
+        Err(J4RsError::GeneralError(format!("{}: unsupported repository scheme", url)))
+    };
+    match written {
+        Ok(()) => Ok(fs::rename(&partial, target)?),
+        Err(error) => {
+            let _ = fs::remove_file(&partial);
+            Err(error)
+        }
+    }
+}
+
 impl From<&[&str]> for MavenArtifact {
     fn from(slice: &[&str]) -> MavenArtifact {
         MavenArtifact {
@@ -93,6 +199,7 @@ impl From<&[&str]> for MavenArtifact {
             id: slice.get(1).unwrap_or(&"").to_string(),
             version: slice.get(2).unwrap_or(&"").to_string(),
             qualifier: slice.get(3).unwrap_or(&"").to_string(),
+            with_dependencies: false,
         }
     }
 }
@@ -139,8 +246,86 @@ impl From<String> for MavenArtifact {
     }
 }
 
+/// A Maven artifact of a resolved dependency tree, along with its own resolved dependencies.
+///
+/// The `Display` implementation prints the whole tree, similarly to `mvn dependency:tree`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MavenDependency {
+    #[serde(rename = "groupId")]
+    group: String,
+    #[serde(rename = "artifactId")]
+    id: String,
+    version: String,
+    #[serde(rename = "classifier")]
+    qualifier: String,
+    scope: String,
+    dependencies: Vec<MavenDependency>,
+}
+
+impl MavenDependency {
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn qualifier(&self) -> &str {
+        &self.qualifier
+    }
+
+    /// The scope of the dependency (`compile` or `runtime`). It is empty for the root of the tree.
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    /// The direct dependencies of the artifact.
+    pub fn dependencies(&self) -> &[MavenDependency] {
+        &self.dependencies
+    }
+
+    /// The artifacts of the tree, including this one, in `group:id:version[:qualifier]` form.
+    pub fn artifacts(&self) -> Vec<String> {
+        let mut artifacts = vec![self.coordinates()];
+        for dependency in &self.dependencies {
+            artifacts.extend(dependency.artifacts());
+        }
+        artifacts
+    }
+
+    fn coordinates(&self) -> String {
+        if self.qualifier.is_empty() {
+            format!("{}:{}:{}", self.group, self.id, self.version)
+        } else {
+            format!("{}:{}:{}:{}", self.group, self.id, self.version, self.qualifier)
+        }
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter, prefix: &str) -> fmt::Result {
+        for (index, dependency) in self.dependencies.iter().enumerate() {
+            let last = index == self.dependencies.len() - 1;
+            writeln!(f)?;
+            write!(f, "{}{} {} ({})", prefix, if last { "\\-" } else { "+-" }, dependency.coordinates(), dependency.scope)?;
+            dependency.fmt_tree(f, &format!("{}{}", prefix, if last { "   " } else { "|  " }))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MavenDependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.coordinates())?;
+        self.fmt_tree(f, "")
+    }
+}
+
 /// Contains Maven settings and configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MavenSettings {
     pub(crate) repos: Vec<MavenArtifactRepo>
 }
@@ -153,6 +338,13 @@ impl MavenSettings {
         repos.push(MavenArtifactRepo::from(MAVEN_CENTRAL));
         MavenSettings { repos }
     }
+
+    /// Creates new Maven Settings that use only the provided repositories, without the [maven central](https://repo.maven.apache.org/maven2).
+    ///
+    /// Useful for builds that must not access the network, e.g. with an internal mirror or a `file://` repository.
+    pub fn without_maven_central(repos: Vec<MavenArtifactRepo>) -> MavenSettings {
+        MavenSettings { repos }
+    }
 }
 
 impl Default for MavenSettings {
@@ -162,7 +354,7 @@ impl Default for MavenSettings {
 }
 
 /// A repository from which Java artifacts can be fetched.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MavenArtifactRepo {
     pub(crate) id: String,
     pub(crate) uri: String,
@@ -208,6 +400,10 @@ impl From<String> for MavenArtifactRepo {
 
 #[cfg(test)]
 mod provisioning_unit_tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
     use super::*;
 
     #[test]
@@ -231,6 +427,71 @@ mod provisioning_unit_tests {
         assert_eq!(ma3.qualifier, "");
     }
 
+    #[test]
+    fn provision_maven_artifact_from_local_repository() {
+        let dir = env::temp_dir().join("j4rs_provision_from_local_repository");
+        let local_repository = dir.join("repository");
+        let target_dir = dir.join("jassets");
+        let artifact = MavenArtifact::from("io.github.astonbitecode:some-agent:1.0:all");
+        assert_eq!(artifact.jar_name(), "some-agent-1.0-all.jar");
+        let cached = local_repository.join("io").join("github").join("astonbitecode").join("some-agent").join("1.0").join("some-agent-1.0-all.jar");
+        fs::create_dir_all(cached.parent().unwrap()).unwrap();
+
+        assert!(provision_artifact(&artifact, Some(&local_repository), &[], &target_dir).is_err());
+        fs::write(&cached, "agent").unwrap();
+        let provisioned = provision_artifact(&artifact, Some(&local_repository), &[], &target_dir).unwrap();
+        assert_eq!(provisioned, target_dir.join("some-agent-1.0-all.jar"));
+        assert_eq!(fs::read_to_string(&provisioned).unwrap(), "agent");
+        // Deployed artifacts need no local repository
+        assert_eq!(provision_artifact(&artifact, None, &[], &target_dir).unwrap(), provisioned);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn provision_maven_artifact_from_repositories() {
+        let dir = env::temp_dir().join("j4rs_provision_from_repositories");
+        let _ = fs::remove_dir_all(&dir);
+        let file_repo = dir.join("repository");
+        let artifact = MavenArtifact::from("org.example:agent:1.0");
+        let jar_dir = file_repo.join("org").join("example").join("agent").join("1.0");
+        fs::create_dir_all(&jar_dir).unwrap();
+        fs::write(jar_dir.join("agent-1.0.jar"), "from file").unwrap();
+        let missing_repo = MavenArtifactRepo::from(format!("missing::file://{}", dir.join("missing").to_str().unwrap()));
+        let file_repo = MavenArtifactRepo::from(format!("local::file://{}/", file_repo.to_str().unwrap()));
+
+        // The first repository that contains the artifact is used
+        let provisioned = provision_artifact(&artifact, None, &[missing_repo.clone(), file_repo], &dir.join("from_file")).unwrap();
+        assert_eq!(fs::read_to_string(&provisioned).unwrap(), "from file");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let read = stream.read(&mut request).unwrap();
+            let request_line = String::from_utf8_lossy(&request[..read]).lines().next().unwrap_or("").to_string();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\nConnection: close\r\n\r\nfrom http").unwrap();
+            request_line
+        });
+        let http_repo = MavenArtifactRepo::from(format!("http::http://{}/maven2", address));
+        let provisioned = provision_artifact(&artifact, None, &[http_repo], &dir.join("from_http")).unwrap();
+        assert_eq!(fs::read_to_string(&provisioned).unwrap(), "from http");
+        assert_eq!(server.join().unwrap(), "GET /maven2/org/example/agent/1.0/agent-1.0.jar HTTP/1.1");
+
+        let error = provision_artifact(&artifact, None, &[missing_repo], &dir.join("missing_target")).err().unwrap();
+        assert!(format!("{}", error).contains("missing/org/example/agent/1.0/agent-1.0.jar"), "{}", error);
+        assert!(!dir.join("missing_target").join("agent-1.0.jar").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn maven_settings_repos() {
+        let settings = MavenSettings::new(vec![MavenArtifactRepo::from("myrepo::https://myrepo.io")]);
+        assert_eq!(settings.repos.iter().map(|repo| repo.id.as_str()).collect::<Vec<&str>>(), vec!["myrepo", "MavenCentral"]);
+        let settings = MavenSettings::without_maven_central(vec![MavenArtifactRepo::from("myrepo::https://myrepo.io")]);
+        assert_eq!(settings.repos.iter().map(|repo| repo.id.as_str()).collect::<Vec<&str>>(), vec!["myrepo"]);
+    }
+
     #[test]
     fn maven_artifact_repo_from() {
         let mar = MavenArtifactRepo::from("myrepo::https://myrepo.io");
@@ -0,0 +1,120 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::convert::TryFrom;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use jni_sys::{JNIEnv, jobject};
+
+use crate::{api_tweaks as tweaks, errors, Instance, InvocationArg, jni_utils, Jvm, utils};
+use crate::errors::opt_to_res;
+use crate::logger::{debug, error};
+
+const RUST_TASK_CLASS: &str = "org.astonbitecode.j4rs.api.invocation.RustTask";
+const INVOCATION_EXCEPTION_CLASS: &str = "org/astonbitecode/j4rs/errors/InvocationException";
+
+pub(crate) type TaskClosure = dyn Fn(&Jvm) -> errors::Result<Option<InvocationArg>> + Send + Sync;
+
+/// A Rust closure that is executed by Java threads, via the `org.astonbitecode.j4rs.api.invocation.RustTask`.
+///
+/// The native methods of the `RustTask` may be served by a j4rs dynamic library other than the binary that created the task.
+/// This is why the task carries the functions that run and release it: these are always the ones of the creating binary.
+#[repr(C)]
+pub(crate) struct RustTask {
+    run: extern "C" fn(*mut JNIEnv, *const RustTask) -> jobject,
+    release: extern "C" fn(*mut RustTask),
+    closure: Box<TaskClosure>,
+}
+
+impl RustTask {
+    // Creates a RustTask Instance, cast to the provided interface.
+    #[track_caller]
+    pub(crate) fn create_instance(jvm: &Jvm, closure: Box<TaskClosure>, interface: &str) -> errors::Result<Instance> {
+        let task = Box::into_raw(Box::new(RustTask { run: run_task, release: release_task, closure }));
+        let address_arg = InvocationArg::try_from(task as i64)?.into_primitive()?;
+        match jvm.create_instance(RUST_TASK_CLASS, &[address_arg]) {
+            // From now on, the task is released when the Java object is closed or garbage collected
+            Ok(instance) => jvm.cast(&instance, interface),
+            Err(error) => {
+                unsafe { drop(Box::from_raw(task)) };
+                Err(error)
+            }
+        }
+    }
+
+    // Runs the task. Called by the native method `RustTask.runtask`.
+    pub(crate) fn run(jni_env: *mut JNIEnv, task: *const RustTask) -> jobject {
+        unsafe { ((*task).run)(jni_env, task) }
+    }
+
+    // Releases the task. Called by the native method `RustTask.droptask`.
+    pub(crate) fn release(task: *mut RustTask) {
+        unsafe { ((*task).release)(task) }
+    }
+}
+
+extern "C" fn run_task(jni_env: *mut JNIEnv, task: *const RustTask) -> jobject {
+    // Panics must not unwind into the Java frames
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut jvm = Jvm::try_from(jni_env)?;
+        // The thread is owned by Java
+        jvm.detach_thread_on_drop(false);
+        let closure = unsafe { &(*task).closure };
+        match closure(&jvm)? {
+            Some(result) => to_local_ref(jni_env, &result),
+            None => Ok(ptr::null_mut()),
+        }
+    }));
+    match result {
+        Ok(Ok(java_result)) => java_result,
+        Ok(Err(err)) => {
+            throw_invocation_exception(jni_env, &format!("The Rust task failed: {}", err));
+            ptr::null_mut()
+        }
+        Err(_) => {
+            throw_invocation_exception(jni_env, "The Rust task panicked");
+            ptr::null_mut()
+        }
+    }
+}
+
+extern "C" fn release_task(task: *mut RustTask) {
+    debug("Releasing a Rust task");
+    if panic::catch_unwind(AssertUnwindSafe(|| unsafe { drop(Box::from_raw(task)) })).is_err() {
+        error("Panicked while releasing a Rust task");
+    }
+}
+
+// Creates the Java InvocationArg of the result, as a local reference that can be returned to Java
+fn to_local_ref(jni_env: *mut JNIEnv, result: &InvocationArg) -> errors::Result<jobject> {
+    let global = result.as_java_ptr(jni_env)?;
+    unsafe {
+        let local = (opt_to_res((**jni_env).NewLocalRef)?)(jni_env, global);
+        jni_utils::delete_java_ref(jni_env, global);
+        Ok(local)
+    }
+}
+
+fn throw_invocation_exception(jni_env: *mut JNIEnv, message: &str) {
+    let thrown = tweaks::find_class(jni_env, INVOCATION_EXCEPTION_CLASS).and_then(|class| {
+        let message = utils::to_modified_utf8(message);
+        unsafe {
+            Ok((opt_to_res((**jni_env).ThrowNew)?)(jni_env, class, message.as_ptr()))
+        }
+    });
+    if thrown.is_err() {
+        error(&format!("Could not throw an InvocationException to Java: {}", message));
+    }
+}
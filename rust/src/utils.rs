@@ -13,31 +13,52 @@
 // limitations under the License.
 
 use std::{self, fs, str};
-use std::ffi::{CStr, CString};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
 
-use cesu8::{to_java_cesu8, from_java_cesu8};
-use fs_extra::dir::get_dir_content;
 use libc::{self, c_char};
 
-use crate::{cache, errors, InvocationArg};
+use crate::{cache, config, errors, InvocationArg};
 
-pub fn to_rust_string(pointer: *const c_char) -> String {
+pub fn to_utf16(string: &str) -> Vec<u16> {
+    string.encode_utf16().collect()
+}
+
+pub fn from_utf16(utf16: &[u16]) -> errors::Result<String> {
+    String::from_utf16(utf16)
+        .map_err(|error| errors::J4RsError::ParseError(format!("The Java String is not valid UTF-16: {}", error)))
+}
 
-    let slice = unsafe { CStr::from_ptr(pointer).to_bytes() };
-    from_java_cesu8(slice).unwrap().to_string()
+/// Encodes a string to the modified UTF-8 that JNI uses for C strings.
+///
+/// The NULs are encoded with two bytes and the supplementary characters as surrogate pairs.
+pub fn to_modified_utf8(string: &str) -> CString {
+    let mut bytes = Vec::with_capacity(string.len());
+    for unit in string.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push((0xC0 | (unit >> 6)) as u8);
+                bytes.push((0x80 | (unit & 0x3F)) as u8);
+            }
+            _ => {
+                bytes.push((0xE0 | (unit >> 12)) as u8);
+                bytes.push((0x80 | ((unit >> 6) & 0x3F)) as u8);
+                bytes.push((0x80 | (unit & 0x3F)) as u8);
+            }
+        }
+    }
+    // The modified UTF-8 does not contain NUL bytes
+    unsafe { CString::from_vec_unchecked(bytes) }
 }
 
+/// Creates a C String for JNI names and signatures, which never contain NULs.
 pub fn to_c_string(string: &str) -> *mut c_char {
     let cs = CString::new(string.as_bytes()).unwrap();
     cs.into_raw()
 }
 
-pub fn to_c_string_struct(string: &str) -> CString {
-    let enc = to_java_cesu8(string).into_owned();
-    unsafe { CString::from_vec_unchecked(enc) }
-}
-
 pub fn drop_c_string(ptr: *mut c_char) {
     let _ = unsafe { CString::from_raw(ptr) };
 }
@@ -52,6 +73,61 @@ pub fn classpath_sep() -> &'static str {
     ";"
 }
 
+// Whether two paths point to the same location. Paths that do not exist are compared as they are.
+pub(crate) fn same_path(a: &str, b: &str) -> bool {
+    a == b || match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Resolves a classpath entry to the paths that it refers to.
+//
+// Glob patterns, like `lib/*` or `lib/**/*.jar`, resolve to the jar files that they match.
+// Directories resolve to themselves, followed by the jars that they contain. Files resolve to themselves.
+// Entries that refer to nothing resolve to no paths.
+pub(crate) fn resolve_classpath_entry(entry: &str) -> errors::Result<Vec<String>> {
+    if entry.contains(['*', '?', '[']) {
+        let paths = glob::glob(entry)
+            .map_err(|error| errors::J4RsError::ParseError(format!("Invalid classpath entry pattern {}: {}", entry, error)))?;
+        Ok(paths.filter_map(|path| path.ok())
+            .filter(|path| is_jar(path))
+            .filter_map(|path| path.to_str().map(|path| path.to_string()))
+            .collect())
+    } else {
+        let path = Path::new(entry);
+        if path.is_dir() {
+            let mut jars: Vec<String> = fs::read_dir(path)?
+                .filter_map(|dir_entry| dir_entry.ok())
+                .map(|dir_entry| dir_entry.path())
+                .filter(|path| is_jar(path))
+                .filter_map(|path| path.to_str().map(|path| path.to_string()))
+                .collect();
+            jars.sort();
+            jars.insert(0, entry.to_string());
+            Ok(jars)
+        } else if path.exists() {
+            Ok(vec![entry.to_string()])
+        } else {
+            Ok(Vec::new())
+        }
+    }
+}
+
+fn is_jar(path: &Path) -> bool {
+    path.is_file() && path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("jar") || extension.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+// Removes the classpath entries that point to the same location as a previous entry
+pub(crate) fn dedup_classpath(entries: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    entries.into_iter()
+        .filter(|entry| seen.insert(fs::canonicalize(entry).unwrap_or_else(|_| PathBuf::from(entry))))
+        .collect()
+}
+
 pub fn java_library_path() -> errors::Result<String> {
     let default = format!("-Djava.library.path={}", deps_dir()?);
     if cfg!(windows) {
@@ -62,14 +138,37 @@ pub fn java_library_path() -> errors::Result<String> {
 }
 
 pub(crate) fn deps_dir() -> errors::Result<String> {
-    let mut pb = jassets_path()?;
-    pb.pop();
+    let mut pb = deps_parent_path()?;
     pb.push("deps");
     Ok(pb
         .to_str()
         .unwrap_or("./deps/").to_owned())
 }
 
+// The directory that contains the deps directory, which is also the parent of the jassets directory
+#[cfg(not(feature = "embedded-jar"))]
+fn deps_parent_path() -> errors::Result<PathBuf> {
+    let mut pb = jassets_path()?;
+    pb.pop();
+    Ok(pb)
+}
+
+// Only the jar is embedded. If the embedded jar is used, the deps directory is the one that cargo creates for the executable.
+#[cfg(feature = "embedded-jar")]
+fn deps_parent_path() -> errors::Result<PathBuf> {
+    let mut pb = jassets_path()?;
+    if pb != crate::embedded::jassets_path()? {
+        pb.pop();
+        return Ok(pb);
+    }
+    let mut pb = std::env::current_exe()?;
+    pb.pop();
+    if pb.ends_with("deps") {
+        pb.pop();
+    }
+    Ok(pb)
+}
+
 pub(crate) fn jassets_path() -> errors::Result<PathBuf> {
     let pb_opt = {
         let guard = cache::JASSETS_PATH.lock()?;
@@ -81,19 +180,54 @@ pub(crate) fn jassets_path() -> errors::Result<PathBuf> {
     }
 }
 
+// The jassets directory of the `J4RS_JASSETS_PATH` environment variable, or the one where the j4rs jar is extracted,
+// when it is embedded in the binary
+#[cfg(feature = "embedded-jar")]
 pub(crate) fn default_jassets_path() -> errors::Result<PathBuf> {
-    let mut jassets_path = std::env::current_exe()?;
-    let mut tmp_vec = Vec::new();
-
-    while tmp_vec.is_empty() {
-        jassets_path.pop();
-        tmp_vec = get_dir_content(&jassets_path)?.directories.into_iter().filter(|path| path.ends_with("jassets")).collect();
+    match env_jassets_path() {
+        Some(jassets_path) if jassets_path.is_dir() => Ok(jassets_path),
+        _ => crate::embedded::jassets_path(),
     }
+}
+
+// Searches the jassets directory in the following order, unless a base path is defined by the JvmBuilder:
+//
+// 1. The jassets directory of the `J4RS_JASSETS_PATH` environment variable
+// 2. The directory of the executable
+// 3. The cargo target directory, which is up to two levels above the directory of the executable
+//    (e.g. for tests, examples and build scripts)
+#[cfg(not(feature = "embedded-jar"))]
+pub(crate) fn default_jassets_path() -> errors::Result<PathBuf> {
+    find_jassets_path(jassets_candidates(env_jassets_path(), &std::env::current_exe()?))
+}
 
-    jassets_path.push("jassets");
-    Ok(jassets_path)
+#[cfg(not(feature = "embedded-jar"))]
+fn find_jassets_path(candidates: Vec<PathBuf>) -> errors::Result<PathBuf> {
+    candidates.iter()
+        .find(|candidate| candidate.is_dir())
+        .cloned()
+        .ok_or_else(|| errors::J4RsError::GeneralError(format!(
+            "Could not find the jassets directory. Searched in [{}]. Its location can be defined with JvmBuilder::with_base_path or the {} environment variable",
+            candidates.iter().map(|candidate| candidate.display().to_string()).collect::<Vec<String>>().join(", "),
+            config::JASSETS_PATH_ENV)))
 }
 
+// The levels of the directories above the directory of the executable that are searched for the jassets
+#[cfg(not(feature = "embedded-jar"))]
+const JASSETS_SEARCH_LEVELS: usize = 2;
+
+#[cfg(not(feature = "embedded-jar"))]
+fn jassets_candidates(env_jassets_path: Option<PathBuf>, exe: &Path) -> Vec<PathBuf> {
+    env_jassets_path.into_iter()
+        .chain(exe.ancestors().skip(1).take(JASSETS_SEARCH_LEVELS + 1).map(|dir| dir.join("jassets")))
+        .collect()
+}
+
+fn env_jassets_path() -> Option<PathBuf> {
+    std::env::var_os(config::JASSETS_PATH_ENV)
+        .filter(|jassets_path| !jassets_path.is_empty())
+        .map(PathBuf::from)
+}
 
 pub(crate) fn find_j4rs_dynamic_libraries_names() -> errors::Result<Vec<String>> {
     let entries: Vec<String> = find_j4rs_dynamic_libraries_dir_entries()?.iter()
@@ -128,7 +262,8 @@ fn find_j4rs_dynamic_libraries_dir_entries() -> errors::Result<Vec<fs::DirEntry>
             let entry = entry.as_ref().unwrap();
             let file_name = entry.file_name();
             let file_name = file_name.to_str().unwrap();
-            file_name.contains("j4rs") && (
+            // The proc-macro library of j4rs_derive lives in the same directory, but does not contain the native callbacks
+            file_name.contains("j4rs") && !file_name.contains("j4rs_derive") && (
                 file_name.contains(".so") ||
                     file_name.contains(".dll") ||
                     file_name.contains(".dylib"))
@@ -190,4 +325,70 @@ mod utils_unit_tests {
         assert!(primitive_of(&InvocationArg::try_from('c').unwrap()) == Some("char".to_string()));
         assert!(primitive_of(&InvocationArg::try_from(()).unwrap()) == Some("void".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn utf16_conversions() {
+        let s = "a\u{0}b 😀 ελληνικά";
+        assert_eq!(from_utf16(&to_utf16(s)).unwrap(), s);
+        // A lone surrogate is valid in Java, but not in Rust
+        assert!(from_utf16(&[0xD83D]).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "embedded-jar"))]
+    fn jassets_search() {
+        let exe = Path::new("/opt/app/target/debug/build/app-1234/build-script-build");
+        assert_eq!(jassets_candidates(Some(PathBuf::from("/from/env/jassets")), exe), vec![
+            PathBuf::from("/from/env/jassets"),
+            PathBuf::from("/opt/app/target/debug/build/app-1234/jassets"),
+            PathBuf::from("/opt/app/target/debug/build/jassets"),
+            PathBuf::from("/opt/app/target/debug/jassets"),
+        ]);
+        assert_eq!(jassets_candidates(None, Path::new("/app")), vec![PathBuf::from("/jassets")]);
+
+        let error = find_jassets_path(vec![PathBuf::from("/not/existing/jassets"), PathBuf::from("/other/jassets")]).err().unwrap();
+        assert!(format!("{}", error).contains("[/not/existing/jassets, /other/jassets]"), "{}", error);
+        let existing = default_jassets_path().unwrap();
+        assert_eq!(find_jassets_path(vec![PathBuf::from("/not/existing/jassets"), existing.clone()]).unwrap(), existing);
+    }
+
+    #[test]
+    fn same_paths() {
+        let current = std::env::current_dir().unwrap();
+        assert!(same_path(".", current.to_str().unwrap()));
+        assert!(same_path("/not/existing", "/not/existing"));
+        assert!(!same_path("/not/existing", "/not/existing/other"));
+    }
+
+    #[test]
+    fn classpath_entries_resolution() {
+        let dir = std::env::temp_dir().join("j4rs_classpath_entries_resolution");
+        let lib = dir.join("lib");
+        fs::create_dir_all(lib.join("nested")).unwrap();
+        for file in &["b.jar", "a.JAR", "notes.txt", "nested/c.jar"] {
+            fs::write(lib.join(file), "").unwrap();
+        }
+        let lib_str = lib.to_str().unwrap().to_string();
+        let in_lib = |file: &str| lib.join(file).to_str().unwrap().to_string();
+
+        assert_eq!(resolve_classpath_entry(&format!("{}/*", lib_str)).unwrap(), vec![in_lib("a.JAR"), in_lib("b.jar")]);
+        assert_eq!(resolve_classpath_entry(&format!("{}/**/*.jar", lib_str)).unwrap(), vec![in_lib("b.jar"), in_lib("nested/c.jar")]);
+        assert_eq!(resolve_classpath_entry(&lib_str).unwrap(), vec![lib_str.clone(), in_lib("a.JAR"), in_lib("b.jar")]);
+        assert_eq!(resolve_classpath_entry(&in_lib("b.jar")).unwrap(), vec![in_lib("b.jar")]);
+        assert!(resolve_classpath_entry(&in_lib("missing.jar")).unwrap().is_empty());
+        assert!(resolve_classpath_entry(&format!("{}/*.war", lib_str)).unwrap().is_empty());
+        assert!(resolve_classpath_entry("[").is_err());
+
+        let with_duplicate = format!("{}/nested/../b.jar", lib_str);
+        assert_eq!(dedup_classpath(vec![in_lib("b.jar"), with_duplicate, in_lib("a.JAR"), in_lib("b.jar")]), vec![in_lib("b.jar"), in_lib("a.JAR")]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn modified_utf8() {
+        assert_eq!(to_modified_utf8("abc").as_bytes(), b"abc");
+        assert_eq!(to_modified_utf8("a\u{0}").as_bytes(), &[0x61, 0xC0, 0x80]);
+        assert_eq!(to_modified_utf8("é").as_bytes(), "é".as_bytes());
+        assert_eq!(to_modified_utf8("😀").as_bytes(), &[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+    }
+}
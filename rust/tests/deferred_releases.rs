@@ -0,0 +1,54 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The delayed releases are counted for the whole process, so this test runs in its own test binary.
+
+use std::convert::TryFrom;
+use std::thread;
+use std::time::Duration;
+
+use j4rs::{InvocationArg, Jvm, JvmBuilder};
+
+fn drop_on_detached_thread(jvm: &Jvm) {
+    let instance = jvm.create_instance("java.lang.String", &[InvocationArg::try_from("deferred").unwrap()]).unwrap();
+    thread::spawn(move || drop(instance)).join().unwrap();
+}
+
+#[test]
+fn deferred_reference_releases() {
+    let jvm: Jvm = JvmBuilder::new().build().unwrap();
+    let before = Jvm::deferred_reference_releases();
+
+    // Released by the next attached thread that uses j4rs
+    drop_on_detached_thread(&jvm);
+    assert_eq!(Jvm::deferred_reference_releases(), before + 1);
+    assert_eq!(Jvm::pending_reference_releases(), 1);
+    let s: String = jvm.to_rust(jvm.create_instance("java.lang.String", &[InvocationArg::try_from("after").unwrap()]).unwrap()).unwrap();
+    assert_eq!(s, "after");
+    assert_eq!(Jvm::pending_reference_releases(), 0);
+
+    // Released by the reaper, while the attached threads do not use j4rs
+    jvm.handle().unwrap().start_reference_reaper(Duration::from_millis(10)).unwrap();
+    drop_on_detached_thread(&jvm);
+    assert_eq!(Jvm::deferred_reference_releases(), before + 2);
+    let mut pending = Jvm::pending_reference_releases();
+    for _ in 0..100 {
+        if pending == 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+        pending = Jvm::pending_reference_releases();
+    }
+    assert_eq!(pending, 0);
+}
@@ -0,0 +1,33 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The JNI version is requested only when the JVM is created, so this test runs in its own test binary.
+
+use j4rs::{JniVersion, Jvm, JvmBuilder};
+
+#[test]
+fn create_jvm_with_jni_version() {
+    // Older Java runtimes reject the version and the JVM can still be created with another one
+    match JvmBuilder::new().with_jni_version(JniVersion::V21).build() {
+        Ok(jvm) => assert!(jvm.java_version().unwrap().feature() >= 21),
+        Err(error) => assert!(error.to_string().contains("does not support the requested JNI version 21"), "{}", error),
+    }
+
+    let jvm: Jvm = JvmBuilder::new()
+        .with_jni_version(JniVersion::V10)
+        .with_min_java_version(10)
+        .build()
+        .unwrap();
+    assert!(jvm.java_version().unwrap().feature() >= 10);
+}
@@ -0,0 +1,35 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The jvm dynamic library is loaded once per process, so this test runs in its own test binary.
+
+use std::convert::TryFrom;
+use std::fs;
+
+use j4rs::{InvocationArg, Jvm, JvmBuilder};
+
+#[test]
+fn select_jvm_lib() {
+    // Failures to locate the library are errors, not panics
+    let error = format!("{}", JvmBuilder::new().with_jvm_lib("/not/existing/libjvm.so").build().err().unwrap());
+    assert!(error.contains("/not/existing/libjvm.so"), "{}", error);
+    let error = format!("{}", JvmBuilder::new().with_java_home("/not/existing/java").build().err().unwrap());
+    assert!(error.contains("/not/existing/java"), "{}", error);
+
+    let java_home = java_locator::locate_java_home().unwrap();
+    let jvm: Jvm = JvmBuilder::new().with_java_home(&java_home).build().unwrap();
+    let home: String = jvm.to_rust(jvm.invoke_static("java.lang.System", "getProperty", &[InvocationArg::try_from("java.home").unwrap()]).unwrap()).unwrap();
+    let java_home = fs::canonicalize(&java_home).unwrap();
+    assert!(fs::canonicalize(&home).unwrap().starts_with(&java_home), "{} is not in {}", home, java_home.display());
+}
@@ -0,0 +1,50 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The module settings apply only when the JVM is created, so this test runs in its own test binary.
+
+use std::convert::TryFrom;
+
+use j4rs::{InvocationArg, Jvm, JvmBuilder};
+
+// The jar of the module path contains the module of tests/resources/modules/src, built with
+// `javac --release 9 -d <classes> $(find tests/resources/modules/src -name "*.java")` and
+// `jar --create --file tests/resources/modules/jars/j4rs-modular-1.0.jar -C <classes> .`
+#[test]
+fn open_modules_for_reflection() {
+    let modules_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("resources").join("modules").join("jars");
+    let jvm: Jvm = JvmBuilder::new()
+        .module_path_entry(modules_dir.to_str().unwrap())
+        .add_module("org.example.j4rs.modular")
+        .add_opens("java.base", "java.lang")
+        .build()
+        .unwrap();
+
+    // Deep reflection on java.lang needs the package to be opened
+    let string_class = jvm.invoke_static("java.lang.Class", "forName", &[InvocationArg::try_from("java.lang.String").unwrap()]).unwrap();
+    let field = jvm.invoke(&string_class, "getDeclaredField", &[InvocationArg::try_from("value").unwrap()]).unwrap();
+    let accessible = jvm.invoke(&field, "trySetAccessible", &[]).unwrap();
+    assert!(jvm.to_rust::<bool>(accessible).unwrap());
+
+    // Classes that are only on the module path are found
+    assert!(jvm.classpath().unwrap().iter().all(|entry| !entry.contains("j4rs-modular")));
+    let greeting = jvm.create_instance("org.example.j4rs.modular.Greeting", &[]).unwrap();
+    let greeted = jvm.invoke(&greeting, "greet", &[InvocationArg::try_from("j4rs").unwrap()]).unwrap();
+    assert_eq!(jvm.to_rust::<String>(greeted).unwrap(), "Hello from the module path, j4rs");
+    let greeting_class = jvm.invoke(&greeting, "getClass", &[]).unwrap();
+    let module = jvm.invoke(&greeting_class, "getModule", &[]).unwrap();
+    let module_name = jvm.invoke(&module, "getName", &[]).unwrap();
+    assert_eq!(jvm.to_rust::<String>(module_name).unwrap(), "org.example.j4rs.modular");
+    assert!(jvm.system_property("jdk.module.path").unwrap().is_some());
+}
@@ -0,0 +1,56 @@
+// Copyright 2020 astonbitecode
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Destroying the JVM affects the whole process, so this test runs in its own test binary.
+
+use std::convert::TryFrom;
+use std::ptr;
+use std::thread;
+
+use j4rs::{InvocationArg, Jvm, JvmBuilder, JvmHandle};
+
+#[test]
+fn destroy_jvm() {
+    let jvm: Jvm = JvmBuilder::new().build().unwrap();
+    let handle = jvm.handle().unwrap();
+    let instance = jvm.create_instance("java.lang.String", &[InvocationArg::try_from("alive").unwrap()]).unwrap();
+    let weak = instance.downgrade().unwrap();
+    let other_jvm = Jvm::attach_thread().unwrap();
+    let chaining_jvm = Jvm::attach_thread().unwrap();
+    let chained = chaining_jvm.chain(chaining_jvm.create_instance("java.lang.Object", &[]).unwrap());
+    let arg = InvocationArg::try_from("alive").unwrap();
+
+    jvm.destroy().unwrap();
+
+    // Everything is poisoned
+    let error = format!("{}", other_jvm.invoke(&instance, "length", &[]).err().unwrap());
+    assert!(error.contains("destroyed"), "{}", error);
+    assert!(other_jvm.create_instance("java.lang.Object", &[]).is_err());
+    assert!(instance.is_null().is_err());
+    assert!(chained.is_null().is_err());
+    assert!(arg.as_java_ptr(ptr::null_mut()).is_err());
+    assert!(InvocationArg::try_from("dead").is_err());
+    assert!(weak.upgrade().is_err());
+    assert!(Jvm::attach_thread().is_err());
+    assert!(JvmBuilder::new().build().is_err());
+    assert!(thread::spawn(move || handle.jvm().is_err()).join().unwrap());
+    assert!(JvmHandle::default_jvm().is_err());
+    assert!(other_jvm.destroy().is_err());
+
+    // Dropping does not crash
+    drop(instance);
+    drop(chained);
+    drop(arg);
+    drop(weak);
+}
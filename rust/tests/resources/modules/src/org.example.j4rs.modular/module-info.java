@@ -0,0 +1,4 @@
+// A module that is only on the module path of the jvm_modules test
+module org.example.j4rs.modular {
+    exports org.example.j4rs.modular;
+}
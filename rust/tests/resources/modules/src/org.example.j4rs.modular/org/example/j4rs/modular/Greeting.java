@@ -0,0 +1,7 @@
+package org.example.j4rs.modular;
+
+public class Greeting {
+    public String greet(String name) {
+        return "Hello from the module path, " + name;
+    }
+}